@@ -18,16 +18,263 @@
 //! This is a very simply command-line interface for the GPX-to-KML converter.
 
 use std::{
-    io::{stdin, stdout},
+    env, fs,
+    io::{stdin, stdout, Write},
+    path::PathBuf,
     process::ExitCode,
+    time::Instant,
 };
 
-use gpx_kml_convert::convert;
+use gpx::Gpx;
+use gpx_kml_convert::{
+    convert_gpx, convert_with_options, diff_gpx_kml, parse_gpx, ConversionOptions,
+    ConversionReport, DiffItem, Error, InputFormat, ProgressStyle, Warning,
+};
+use kml::{Kml, KmlReader};
+use serde_json::{json, Value};
+
+/// Usage information printed for `--help`.
+const USAGE: &str = "\
+Usage: gpx_kml_convert_cli [OPTIONS] < INPUT.gpx > OUTPUT.kml
+
+Convert a GPX file from STDIN to a KML file on STDOUT.
+
+Options:
+  -v, --verbose                    Print a report about the conversion to STDERR
+  -p, --progress                   Display a progress bar on STDERR
+      --report-json <OUTPUT.json>  Write a report about the conversion as JSON
+      --input-format <FORMAT>      Parse the input as gpx1.0, gpx1.1, or auto
+                                   [default: auto]
+      --diff                       Print a report about data lost in the
+                                   conversion to STDERR
+  -h, --help                       Print this help
+
+By default, the GPX version is taken from the version attribute of the input.
+Files declaring a wrong version can be parsed by forcing one, e.g.:
+  gpx_kml_convert_cli --input-format gpx1.0 < legacy.gpx > legacy.kml";
+
+/// Parsed command-line arguments.
+#[derive(Default)]
+struct Args {
+    /// Print a report about the conversion to STDERR.
+    verbose: bool,
+    /// Display a progress bar on STDERR.
+    progress: bool,
+    /// Write a JSON report about the conversion to this file.
+    report_json: Option<PathBuf>,
+    /// GPX version for parsing the input.
+    input_format: InputFormat,
+    /// Print a report about data lost in the conversion to STDERR.
+    diff: bool,
+    /// Only print the usage information.
+    help: bool,
+}
+
+impl Args {
+    /// Parse the arguments of this process.
+    fn parse() -> Result<Self, String> {
+        let mut args = Self::default();
+        let mut iter = env::args().skip(1);
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "-v" | "--verbose" => args.verbose = true,
+                "-p" | "--progress" => args.progress = true,
+                "--report-json" => {
+                    let path = iter.next().ok_or("missing path for --report-json")?;
+                    args.report_json = Some(path.into());
+                }
+                "--input-format" => {
+                    let format = iter.next().ok_or("missing format for --input-format")?;
+                    args.input_format = match format.as_str() {
+                        "gpx1.0" => InputFormat::Gpx10,
+                        "gpx1.1" => InputFormat::Gpx11,
+                        "auto" => InputFormat::Auto,
+                        _ => return Err(format!("unknown input format: {format}")),
+                    };
+                }
+                "--diff" => args.diff = true,
+                "-h" | "--help" => args.help = true,
+                _ => return Err(format!("unknown argument: {arg}")),
+            }
+        }
+        Ok(args)
+    }
+}
+
+/// Serialize the `warning` as JSON object.
+///
+/// The `kind` field contains the name of the variant.
+fn warning_json(warning: &Warning) -> Value {
+    match warning {
+        Warning::TrackDroppedTooOld { name, age_days } => {
+            json!({ "kind": "TrackDroppedTooOld", "name": name, "age_days": age_days })
+        }
+        Warning::TrackDroppedTooShort { name, length_m } => {
+            json!({ "kind": "TrackDroppedTooShort", "name": name, "length_m": length_m })
+        }
+        Warning::TrackDroppedTooBrief { name, duration_s } => {
+            json!({ "kind": "TrackDroppedTooBrief", "name": name, "duration_s": duration_s })
+        }
+        Warning::RouteDroppedTooShort { name, length_m } => {
+            json!({ "kind": "RouteDroppedTooShort", "name": name, "length_m": length_m })
+        }
+    }
+}
+
+/// Serialize the `report` as JSON.
+///
+/// Durations are given in seconds. The coordinates of an empty bounding box
+/// are `null`.
+fn report_json(report: &ConversionReport) -> String {
+    let bounding_box = &report.bounding_box;
+    let value = json!({
+        "parse_duration_secs": report.parse_duration.as_secs_f64(),
+        "convert_duration_secs": report.convert_duration.as_secs_f64(),
+        "waypoint_count": report.waypoint_count,
+        "route_count": report.route_count,
+        "track_count": report.track_count,
+        "warnings": report.warnings.iter().map(warning_json).collect::<Vec<_>>(),
+        "bounding_box": {
+            "min_lat": bounding_box.min_lat,
+            "max_lat": bounding_box.max_lat,
+            "min_lon": bounding_box.min_lon,
+            "max_lon": bounding_box.max_lon,
+        },
+    });
+    format!("{value:#}\n")
+}
+
+/// Convert from STDIN to `sink` like [`convert_with_options`].
+///
+/// The parsed GPX data is returned, too, so that it can be compared with the
+/// KML output.
+fn convert_keeping_gpx(
+    sink: &mut Vec<u8>,
+    options: &ConversionOptions,
+) -> Result<(ConversionReport, Gpx), Error> {
+    let start = Instant::now();
+    let gpx = parse_gpx(stdin(), options)?;
+    let parse_duration = start.elapsed();
+
+    let start = Instant::now();
+    let mut report = convert_gpx(gpx.clone(), sink, options)?;
+    report.parse_duration = parse_duration;
+    report.convert_duration = start.elapsed();
+    Ok((report, gpx))
+}
+
+/// Summarize the data of `gpx` lost in its conversion to `kml`.
+///
+/// The KML output is parsed again and compared with [`diff_gpx_kml`]. Losses
+/// that a [`ConversionOptions`] field of the library mitigates are followed by
+/// a hint on that field, as the CLI has no flags for them.
+fn diff_report(gpx: &Gpx, kml: &[u8]) -> Result<String, String> {
+    let kml = match KmlReader::<_, f64>::from_reader(kml).read() {
+        Ok(Kml::KmlDocument(document)) => document,
+        Ok(_) => return Err("KML root missing".to_string()),
+        Err(err) => return Err(format!("reading KML failed: {err}")),
+    };
 
-/// Currently, this simply converts from STDIN to STDOUT.
+    let (mut timestamps, mut symbols, mut extensions, mut elevations) = (0, 0, 0, 0);
+    for item in diff_gpx_kml(gpx, &kml) {
+        match item {
+            DiffItem::TrackTimestampLost { .. } => timestamps += 1,
+            DiffItem::WaypointSymbolLost { .. } => symbols += 1,
+            DiffItem::ExtensionDataLost { .. } => extensions += 1,
+            DiffItem::ElevationDiscrepancy { .. } => elevations += 1,
+        }
+    }
+
+    let mut report = format!(
+        "{timestamps} track timestamps lost (KML lines cannot store them, \
+        the library option ConversionOptions::include_statistics records \
+        only the total duration), \
+        {symbols} waypoint symbols lost (no icon mapping configured, \
+        the library option ConversionOptions::osmand_compatible maps them \
+        to icons), "
+    );
+    if extensions > 0 {
+        report += &format!("{extensions} extensions lost (not supported), ");
+    }
+    // The CLI keeps zero altitudes and uses `f64` coordinates, so no option
+    // would reduce the remaining discrepancies.
+    report += &format!("{elevations} elevation discrepancies.");
+    Ok(report)
+}
+
+/// Convert from STDIN to STDOUT.
+///
+/// Reports about the conversion are printed to STDERR with `--verbose` and
+/// `--diff` or written as JSON with `--report-json`. `--progress` displays a
+/// progress bar and `--input-format` forces the GPX version of the input.
 fn main() -> ExitCode {
-    match convert(&mut stdin(), &mut stdout()) {
-        Ok(()) => ExitCode::SUCCESS,
+    let args = match Args::parse() {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{err}\n\n{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+    if args.help {
+        println!("{USAGE}");
+        return ExitCode::SUCCESS;
+    }
+
+    let mut options = ConversionOptions {
+        input_format: args.input_format,
+        ..Default::default()
+    };
+    if args.progress {
+        options.progress = ProgressStyle::ProgressBar;
+    }
+
+    // The diff needs both the GPX input and the KML output in memory.
+    let mut input = None;
+    let mut output = vec![];
+    let result = if args.diff {
+        convert_keeping_gpx(&mut output, &options).map(|(report, gpx)| {
+            input = Some(gpx);
+            report
+        })
+    } else {
+        convert_with_options(&mut stdin(), &mut stdout(), &options)
+    };
+
+    match result {
+        Ok(report) => {
+            if let Some(gpx) = input {
+                if let Err(err) = stdout().write_all(&output) {
+                    eprintln!("Writing KML failed with: {err}");
+                    return ExitCode::FAILURE;
+                }
+                match diff_report(&gpx, &output) {
+                    Ok(diff) => eprintln!("{diff}"),
+                    Err(err) => {
+                        eprintln!("Diffing failed with: {err}");
+                        return ExitCode::FAILURE;
+                    }
+                }
+            }
+            if args.verbose {
+                eprintln!("Parsing GPX took {:?}", report.parse_duration);
+                eprintln!("Converting to KML took {:?}", report.convert_duration);
+                eprintln!(
+                    "Converted {} waypoints, {} routes, and {} tracks",
+                    report.waypoint_count, report.route_count, report.track_count
+                );
+                eprintln!("Bounding box: {}", report.bounding_box);
+                for warning in &report.warnings {
+                    eprintln!("Warning: {warning}");
+                }
+            }
+            if let Some(path) = args.report_json {
+                if let Err(err) = fs::write(&path, report_json(&report)) {
+                    eprintln!("Writing report to {} failed with: {err}", path.display());
+                    return ExitCode::FAILURE;
+                }
+            }
+            ExitCode::SUCCESS
+        }
         Err(err) => {
             eprintln!("Conversion failed with: {err:?}");
             ExitCode::FAILURE
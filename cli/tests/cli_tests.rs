@@ -0,0 +1,68 @@
+// Copyright 2023 Viktor Reusch
+//
+// This file is part of gpx_kml_convert.
+//
+// gpx_kml_convert is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, either version 3 of the License, or (at your
+// option) any later version.
+//
+// gpx_kml_convert is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License
+// for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with gpx_kml_convert. If not, see <https://www.gnu.org/licenses/>.
+
+//! Tests running the command-line interface.
+
+use std::{
+    io::Write,
+    process::{Command, Output, Stdio},
+};
+
+/// GPX data which can only be parsed with a forced version.
+const LEGACY: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx xmlns="http://www.topografix.com/GPX/1/1" version="1.1.0" creator="legacy">
+    <wpt lat="48.858222" lon="2.2945"><ele>30</ele><name>Eiffel Tower</name><sym>Flag</sym></wpt>
+    <trk><trkseg>
+        <trkpt lat="47.0" lon="8.0"><time>2023-06-01T08:00:00Z</time></trkpt>
+        <trkpt lat="47.01" lon="8.0"><time>2023-06-01T08:10:00Z</time></trkpt>
+    </trkseg></trk>
+</gpx>
+"#;
+
+/// Run the CLI with `args` and `input` on STDIN.
+fn run(args: &[&str], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_gpx_kml_convert_cli"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("CLI could not be started");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn diff_with_input_format() {
+    assert!(!run(&["--diff"], LEGACY).status.success());
+
+    let output = run(&["--diff", "--input-format", "gpx1.1"], LEGACY);
+    assert!(output.status.success());
+    let kml = String::from_utf8(output.stdout).unwrap();
+    assert!(kml.contains("<name>Eiffel Tower</name>"));
+    let diff = String::from_utf8(output.stderr).unwrap();
+    assert!(diff.contains("1 track timestamps lost"));
+    assert!(diff.contains("1 waypoint symbols lost"));
+    assert!(diff.contains("ConversionOptions::osmand_compatible"));
+    assert!(!diff.contains("omit_zero_altitude"));
+    assert!(diff.contains("0 elevation discrepancies"));
+}
@@ -0,0 +1,126 @@
+// Copyright 2023 Viktor Reusch
+//
+// This file is part of gpx_kml_convert.
+//
+// gpx_kml_convert is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, either version 3 of the License, or (at your
+// option) any later version.
+//
+// gpx_kml_convert is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License
+// for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with gpx_kml_convert. If not, see <https://www.gnu.org/licenses/>.
+
+//! Wrapping of the KML output in an [Atom](https://www.w3.org/2005/Atom) feed.
+
+use std::io;
+
+use gpx::Metadata;
+use kml::{Kml, KmlWriter};
+
+use crate::{format_time, simple_kelem, CoordValue, ATOM_NAMESPACE, ATOM_URI};
+
+/// Update time used if the GPX metadata has none.
+const FALLBACK_UPDATED: &str = "1970-01-01T00:00:00Z";
+/// Prefix of the generated feed IDs.
+const ID_PREFIX: &str = "urn:gpx_kml_convert:";
+
+/// Header of an Atom feed containing the KML output.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct AtomFeed {
+    title: String,
+    id: String,
+    updated: String,
+}
+
+impl AtomFeed {
+    /// Derive the feed header from the GPX `metadata`.
+    ///
+    /// The `title` replaces the metadata name if given. The ID is the first
+    /// metadata link or otherwise generated from the title and update time.
+    pub(crate) fn new(metadata: Option<&Metadata>, title: Option<&str>) -> Self {
+        let title = title
+            .map(str::to_string)
+            .or_else(|| metadata.and_then(|m| m.name.clone()))
+            .unwrap_or_default();
+        let updated = metadata
+            .and_then(|m| m.time)
+            .and_then(format_time)
+            .unwrap_or_else(|| FALLBACK_UPDATED.to_string());
+        let id = metadata
+            .and_then(|m| m.links.first())
+            .map(|link| link.href.clone())
+            .unwrap_or_else(|| format!("{ID_PREFIX}{:016x}", fnv1a([&title, &updated])));
+        Self { title, id, updated }
+    }
+
+    /// Write the feed containing `kml` to `sink`.
+    ///
+    /// The KML data is written as extension element of the feed.
+    pub(crate) fn write<T: CoordValue>(
+        &self,
+        kml: &Kml<T>,
+        mut sink: impl io::Write,
+    ) -> Result<(), kml::Error> {
+        write!(sink, r#"<atom:feed {ATOM_NAMESPACE}="{ATOM_URI}">"#)?;
+        let mut writer = KmlWriter::from_writer(&mut sink);
+        writer.write(&simple_kelem("atom:title", &self.title))?;
+        writer.write(&simple_kelem("atom:id", &self.id))?;
+        writer.write(&simple_kelem("atom:updated", &self.updated))?;
+        writer.write(kml)?;
+        write!(sink, "</atom:feed>")?;
+        Ok(())
+    }
+}
+
+/// Hash `parts` with the 64-bit FNV-1a function.
+///
+/// Unlike the hashers of the standard library, this is stable across Rust
+/// versions, so the same GPX data always results in the same feed ID.
+fn fnv1a<'a>(parts: impl IntoIterator<Item = &'a String>) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for part in parts {
+        // Separate the parts so that moving characters between them changes
+        // the hash.
+        for byte in part.bytes().chain([0]) {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn feed_header() {
+        let feed = AtomFeed::new(None, None);
+        assert_eq!(feed.title, "");
+        assert_eq!(feed.updated, FALLBACK_UPDATED);
+        assert!(feed.id.starts_with(ID_PREFIX));
+        assert_eq!(feed, AtomFeed::new(None, None));
+
+        let metadata = Metadata {
+            name: Some("Hike".to_string()),
+            ..Default::default()
+        };
+        let named = AtomFeed::new(Some(&metadata), None);
+        assert_eq!(named.title, "Hike");
+        assert_ne!(named.id, feed.id);
+
+        let renamed = AtomFeed::new(Some(&metadata), Some("Walk"));
+        assert_eq!(renamed.title, "Walk");
+    }
+
+    #[test]
+    fn stable_hash() {
+        assert_eq!(fnv1a([]), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a([&"a".to_string()]), 0x089b_e207_b544_f1e4);
+    }
+}
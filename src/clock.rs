@@ -0,0 +1,57 @@
+// Copyright 2023 Viktor Reusch
+//
+// This file is part of gpx_kml_convert.
+//
+// gpx_kml_convert is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, either version 3 of the License, or (at your
+// option) any later version.
+//
+// gpx_kml_convert is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License
+// for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with gpx_kml_convert. If not, see <https://www.gnu.org/licenses/>.
+
+//! Access to clocks, which are not available on every platform.
+//!
+//! [`Instant::now`] and [`SystemTime::now`] panic on `wasm32-unknown-unknown`.
+//! There, measured durations are zero and the system time is unknown.
+
+use std::time::Duration;
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use std::time::{Instant, SystemTime};
+
+/// Measure the time elapsed since the creation of the stopwatch.
+pub(crate) struct Stopwatch {
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    start: Instant,
+}
+
+impl Stopwatch {
+    /// Start measuring.
+    pub(crate) fn start() -> Self {
+        Self {
+            #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+            start: Instant::now(),
+        }
+    }
+
+    /// Time elapsed since the start, or zero if no clock is available.
+    pub(crate) fn elapsed(&self) -> Duration {
+        #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+        return self.start.elapsed();
+        #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+        Duration::ZERO
+    }
+}
+
+/// Current system time if it is available.
+pub(crate) fn system_time() -> Option<std::time::SystemTime> {
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    return Some(SystemTime::now());
+    #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+    None
+}
@@ -0,0 +1,214 @@
+// Copyright 2023 Viktor Reusch
+//
+// This file is part of gpx_kml_convert.
+//
+// gpx_kml_convert is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, either version 3 of the License, or (at your
+// option) any later version.
+//
+// gpx_kml_convert is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License
+// for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with gpx_kml_convert. If not, see <https://www.gnu.org/licenses/>.
+
+//! Detection of GPX data lost in the conversion to KML.
+
+use gpx::{Gpx, Waypoint};
+use kml::types::{Coord, Geometry, Placemark};
+use kml::{Kml, KmlDocument};
+
+/// Elevation difference in meters up to which points are considered equal.
+const ELEVATION_TOLERANCE_M: f64 = 0.01;
+
+/// GPX data missing in the KML output.
+///
+/// This is returned from [`diff_gpx_kml`]. Features are referenced by their
+/// index in the GPX data, e.g., `tracks[2]`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DiffItem {
+    /// The track has point timestamps but its placemark has no time data.
+    TrackTimestampLost {
+        /// Index of the track in the GPX data.
+        track_index: usize,
+    },
+    /// The waypoint has a symbol but its placemark has no icon.
+    WaypointSymbolLost {
+        /// Index of the waypoint in the GPX data.
+        waypoint_index: usize,
+        /// GPX symbol of the waypoint.
+        sym: String,
+    },
+    /// The feature has GPX extensions which are missing in the KML output.
+    ///
+    /// This is currently never reported because the `gpx` crate skips
+    /// extensions while parsing.
+    ExtensionDataLost {
+        /// Path of the feature like `waypoints[0]`.
+        feature_path: String,
+    },
+    /// The elevations of the feature differ between GPX and KML.
+    ///
+    /// Points without elevation in the KML output count as zero elevation.
+    ElevationDiscrepancy {
+        /// Path of the feature like `tracks[1]`.
+        feature_path: String,
+        /// Largest elevation difference of all points in meters.
+        delta_m: f64,
+    },
+}
+
+/// Compare the `gpx` data to the `kml` output of its conversion.
+///
+/// The placemarks of the KML document are matched in order with the GPX
+/// features: _Points_ with waypoints, _LineStrings_ with routes, and
+/// _MultiGeometries_ with tracks. GPX features without a matching placemark
+/// are not reported. Options changing the number of placemarks, like
+/// [`split_on_elevation_change`](crate::ConversionOptions::split_on_elevation_change),
+/// therefore result in unreliable differences.
+///
+/// # Example
+/// ```
+/// # use gpx_kml_convert::{convert, diff_gpx_kml, DiffItem};
+/// # use kml::{Kml, KmlReader};
+/// #
+/// let source = r#"<?xml version="1.0" encoding="UTF-8"?>
+/// <gpx xmlns="http://www.topografix.com/GPX/1/1" version="1.1">
+///     <wpt lat="48.858222" lon="2.2945"><sym>Flag, Blue</sym></wpt>
+/// </gpx>
+/// "#;
+/// let gpx = gpx::read(source.as_bytes()).expect("GPX is invalid");
+/// let mut kml = vec![];
+/// convert(source.as_bytes(), &mut kml).expect("conversion failed");
+/// let Kml::KmlDocument(kml) = KmlReader::<_, f64>::from_reader(kml.as_slice()).read().unwrap()
+/// else {
+///     panic!("KML root missing");
+/// };
+///
+/// let diff = diff_gpx_kml(&gpx, &kml);
+/// assert_eq!(
+///     diff,
+///     [DiffItem::WaypointSymbolLost { waypoint_index: 0, sym: "Flag, Blue".to_string() }]
+/// );
+/// ```
+pub fn diff_gpx_kml(gpx: &Gpx, kml: &KmlDocument<f64>) -> Vec<DiffItem> {
+    let mut placemarks = vec![];
+    for element in &kml.elements {
+        collect(element, &mut placemarks);
+    }
+    let with_geometry = |matches: fn(&Geometry) -> bool| {
+        placemarks
+            .iter()
+            .copied()
+            .filter(move |p| p.geometry.as_ref().is_some_and(matches))
+    };
+
+    let mut diff = vec![];
+    let points = with_geometry(|g| matches!(g, Geometry::Point(_)));
+    for (index, (waypoint, placemark)) in gpx.waypoints.iter().zip(points).enumerate() {
+        if let Some(sym) = &waypoint.symbol {
+            if !has_child(placemark, &["icon", "osmand:icon", "styleUrl", "Style"]) {
+                diff.push(DiffItem::WaypointSymbolLost {
+                    waypoint_index: index,
+                    sym: sym.clone(),
+                });
+            }
+        }
+        diff.extend(elevation_discrepancy(
+            format!("waypoints[{index}]"),
+            [waypoint],
+            coords(placemark),
+        ));
+    }
+
+    let lines = with_geometry(|g| matches!(g, Geometry::LineString(_)));
+    for (index, (route, placemark)) in gpx.routes.iter().zip(lines).enumerate() {
+        diff.extend(elevation_discrepancy(
+            format!("routes[{index}]"),
+            &route.points,
+            coords(placemark),
+        ));
+    }
+
+    let multis = with_geometry(|g| matches!(g, Geometry::MultiGeometry(_)));
+    for (index, (track, placemark)) in gpx.tracks.iter().zip(multis).enumerate() {
+        let points = track.segments.iter().flat_map(|s| &s.points);
+        if points.clone().any(|p| p.time.is_some())
+            && !has_child(placemark, &["TimeSpan", "TimeStamp", "Track", "gx:Track"])
+        {
+            diff.push(DiffItem::TrackTimestampLost { track_index: index });
+        }
+        diff.extend(elevation_discrepancy(
+            format!("tracks[{index}]"),
+            points,
+            coords(placemark),
+        ));
+    }
+    diff
+}
+
+/// Recursively collect the placemarks in `kml`.
+fn collect<'a>(kml: &'a Kml, placemarks: &mut Vec<&'a Placemark>) {
+    match kml {
+        Kml::KmlDocument(document) => document
+            .elements
+            .iter()
+            .for_each(|e| collect(e, placemarks)),
+        Kml::Document { elements, .. } | Kml::Folder { elements, .. } => {
+            elements.iter().for_each(|e| collect(e, placemarks));
+        }
+        Kml::Placemark(placemark) => placemarks.push(placemark),
+        _ => {}
+    }
+}
+
+/// Whether the `placemark` has a child element with one of the `names`.
+fn has_child(placemark: &Placemark, names: &[&str]) -> bool {
+    placemark
+        .children
+        .iter()
+        .any(|c| names.contains(&c.name.as_str()))
+}
+
+/// Get all coordinates of the `placemark` in order.
+fn coords(placemark: &Placemark) -> Vec<&Coord> {
+    fn flatten<'a>(geometry: &'a Geometry, coords: &mut Vec<&'a Coord>) {
+        match geometry {
+            Geometry::Point(point) => coords.push(&point.coord),
+            Geometry::LineString(line) => coords.extend(&line.coords),
+            Geometry::MultiGeometry(multi) => {
+                multi.geometries.iter().for_each(|g| flatten(g, coords));
+            }
+            _ => {}
+        }
+    }
+
+    let mut coords = vec![];
+    if let Some(geometry) = &placemark.geometry {
+        flatten(geometry, &mut coords);
+    }
+    coords
+}
+
+/// Compare the elevations of the `points` of a feature to the `coords` of its
+/// placemark.
+///
+/// Returns a discrepancy for the `feature_path` if any elevation differs.
+fn elevation_discrepancy<'a>(
+    feature_path: String,
+    points: impl IntoIterator<Item = &'a Waypoint>,
+    coords: Vec<&Coord>,
+) -> Option<DiffItem> {
+    let delta_m = points
+        .into_iter()
+        .zip(coords)
+        .filter_map(|(p, c)| Some((p.elevation? - c.z.unwrap_or(0.0)).abs()))
+        .fold(0.0, f64::max);
+    (delta_m > ELEVATION_TOLERANCE_M).then_some(DiffItem::ElevationDiscrepancy {
+        feature_path,
+        delta_m,
+    })
+}
@@ -0,0 +1,207 @@
+// Copyright 2023 Viktor Reusch
+//
+// This file is part of gpx_kml_convert.
+//
+// gpx_kml_convert is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, either version 3 of the License, or (at your
+// option) any later version.
+//
+// gpx_kml_convert is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License
+// for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with gpx_kml_convert. If not, see <https://www.gnu.org/licenses/>.
+
+//! Configuration of the [`ConversionOptions`] via environment variables.
+
+use std::ffi::OsString;
+use std::str::FromStr;
+
+use kml::types::AltitudeMode;
+use thiserror::Error;
+
+use crate::{ConversionOptions, DistanceUnits};
+
+/// Prefix of all environment variables read by
+/// [`ConversionOptions::from_env`].
+const PREFIX: &str = "GPX_KML_";
+
+/// Errors of [`ConversionOptions::from_env`].
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum EnvConfigError {
+    /// An environment variable has a value which cannot be parsed.
+    #[error("invalid value {value:?} for {var}")]
+    InvalidValue {
+        /// Name of the environment variable.
+        var: String,
+        /// Value of the environment variable.
+        ///
+        /// Invalid Unicode is replaced by `U+FFFD`.
+        value: String,
+    },
+}
+
+impl ConversionOptions {
+    /// Create the options from environment variables.
+    ///
+    /// Options without a variable keep their [`Default`]. The following
+    /// variables are read:
+    ///
+    /// - `GPX_KML_TESSELLATE`: [`tessellate`](Self::tessellate) as `true` or
+    ///   `false`
+    /// - `GPX_KML_ALTITUDE_MODE`: [`altitude_mode`](Self::altitude_mode) as
+    ///   `clampToGround`, `relativeToGround`, or `absolute`
+    /// - `GPX_KML_COORDINATE_DISPLAY_PRECISION`:
+    ///   [`coordinate_display_precision`](Self::coordinate_display_precision)
+    ///   of the descriptions, not of the KML coordinates
+    /// - `GPX_KML_UNITS`: [`units`](Self::units) as `metric` or `imperial`
+    /// - `GPX_KML_INCLUDE_STATISTICS`:
+    ///   [`include_statistics`](Self::include_statistics) as `true` or `false`
+    /// - `GPX_KML_REQUIRE_CONTENT`: [`require_content`](Self::require_content)
+    ///   as `true` or `false`
+    /// - `GPX_KML_OMIT_ZERO_ALTITUDE`:
+    ///   [`omit_zero_altitude`](Self::omit_zero_altitude) as `true` or `false`
+    ///
+    /// Other variables are ignored. Options set in code take precedence when
+    /// the result is used as base of the struct update syntax.
+    ///
+    /// # Errors
+    /// Fails with [`EnvConfigError::InvalidValue`] if a variable cannot be
+    /// parsed.
+    ///
+    /// # Example
+    /// ```
+    /// # use gpx_kml_convert::ConversionOptions;
+    /// #
+    /// std::env::set_var("GPX_KML_COORDINATE_DISPLAY_PRECISION", "6");
+    /// std::env::set_var("GPX_KML_TESSELLATE", "false");
+    ///
+    /// let options = ConversionOptions {
+    ///     tessellate: true,
+    ///     ..ConversionOptions::from_env().expect("invalid environment")
+    /// };
+    /// assert_eq!(options.coordinate_display_precision, 6);
+    /// assert!(options.tessellate);
+    /// ```
+    pub fn from_env() -> Result<Self, EnvConfigError> {
+        Self::from_vars(|name| std::env::var_os(name))
+    }
+
+    /// Implementation of [`from_env`](Self::from_env) reading variables with
+    /// `var`.
+    fn from_vars(var: impl Fn(&str) -> Option<OsString>) -> Result<Self, EnvConfigError> {
+        let read = |name: &str| {
+            let name = format!("{PREFIX}{name}");
+            var(&name).map(|value| (name, value.to_string_lossy().into_owned()))
+        };
+
+        let mut options = Self::default();
+        if let Some(tessellate) = parse(read("TESSELLATE"))? {
+            options.tessellate = tessellate;
+        }
+        if let Some(mode) = parse::<AltitudeMode>(read("ALTITUDE_MODE"))? {
+            options.altitude_mode = Some(mode);
+        }
+        if let Some(precision) = parse(read("COORDINATE_DISPLAY_PRECISION"))? {
+            options.coordinate_display_precision = precision;
+        }
+        if let Some((var, value)) = read("UNITS") {
+            options.units = match value.as_str() {
+                "metric" => DistanceUnits::Metric,
+                "imperial" => DistanceUnits::Imperial,
+                _ => return Err(invalid(var, value)),
+            };
+        }
+        if let Some(include) = parse(read("INCLUDE_STATISTICS"))? {
+            options.include_statistics = include;
+        }
+        if let Some(require) = parse(read("REQUIRE_CONTENT"))? {
+            options.require_content = require;
+        }
+        if let Some(omit) = parse(read("OMIT_ZERO_ALTITUDE"))? {
+            options.omit_zero_altitude = omit;
+        }
+        Ok(options)
+    }
+}
+
+/// Parse the `value` of the variable `var` if it is set.
+fn parse<T: FromStr>(var: Option<(String, String)>) -> Result<Option<T>, EnvConfigError> {
+    var.map(|(var, value)| value.parse().map_err(|_| invalid(var, value)))
+        .transpose()
+}
+
+/// Create the error for an invalid `value` of the variable `var`.
+fn invalid(var: String, value: String) -> EnvConfigError {
+    EnvConfigError::InvalidValue { var, value }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    /// Create the options from the variables `vars`.
+    fn from_vars(vars: &[(&str, &str)]) -> Result<ConversionOptions, EnvConfigError> {
+        let vars: HashMap<_, _> = vars.iter().copied().collect();
+        ConversionOptions::from_vars(|name| vars.get(name).map(OsString::from))
+    }
+
+    #[test]
+    fn defaults() {
+        assert_eq!(from_vars(&[]), Ok(ConversionOptions::default()));
+        let unknown = from_vars(&[("GPX_KML_UNKNOWN", "1"), ("TESSELLATE", "maybe")]);
+        assert_eq!(unknown, Ok(ConversionOptions::default()));
+    }
+
+    #[test]
+    fn values() {
+        let options = from_vars(&[
+            ("GPX_KML_TESSELLATE", "false"),
+            ("GPX_KML_ALTITUDE_MODE", "relativeToGround"),
+            ("GPX_KML_COORDINATE_DISPLAY_PRECISION", "5"),
+            ("GPX_KML_UNITS", "imperial"),
+            ("GPX_KML_INCLUDE_STATISTICS", "true"),
+            ("GPX_KML_REQUIRE_CONTENT", "true"),
+            ("GPX_KML_OMIT_ZERO_ALTITUDE", "true"),
+        ])
+        .unwrap();
+        assert!(!options.tessellate);
+        assert_eq!(options.altitude_mode, Some(AltitudeMode::RelativeToGround));
+        assert_eq!(options.coordinate_display_precision, 5);
+        assert_eq!(options.units, DistanceUnits::Imperial);
+        assert!(options.include_statistics);
+        assert!(options.require_content);
+        assert!(options.omit_zero_altitude);
+    }
+
+    #[test]
+    fn coordinate_display_precision() {
+        let options = from_vars(&[("GPX_KML_COORDINATE_DISPLAY_PRECISION", "7")]).unwrap();
+        assert_eq!(options.coordinate_display_precision, 7);
+        assert_eq!(
+            options.coordinate_type,
+            ConversionOptions::default().coordinate_type
+        );
+
+        let options = from_vars(&[("GPX_KML_COORDINATE_PRECISION", "7")]).unwrap();
+        assert_eq!(options, ConversionOptions::default());
+    }
+
+    #[test]
+    fn invalid_values() {
+        for (var, value) in [
+            ("GPX_KML_TESSELLATE", "yes"),
+            ("GPX_KML_ALTITUDE_MODE", "high"),
+            ("GPX_KML_COORDINATE_DISPLAY_PRECISION", "-1"),
+            ("GPX_KML_UNITS", "nautical"),
+        ] {
+            let expected = invalid(var.to_string(), value.to_string());
+            assert_eq!(from_vars(&[(var, value)]), Err(expected));
+        }
+    }
+}
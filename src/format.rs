@@ -0,0 +1,201 @@
+// Copyright 2023 Viktor Reusch
+//
+// This file is part of gpx_kml_convert.
+//
+// gpx_kml_convert is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, either version 3 of the License, or (at your
+// option) any later version.
+//
+// gpx_kml_convert is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License
+// for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with gpx_kml_convert. If not, see <https://www.gnu.org/licenses/>.
+
+//! Formatting of values for human-readable descriptions.
+
+use std::{fmt::Write, time::Duration};
+
+use crate::geo::TrackStatistics;
+use crate::{ConversionOptions, DistanceUnits};
+
+/// Meters per international mile.
+const METERS_PER_MILE: f64 = 1609.344;
+/// Meters per international foot.
+const METERS_PER_FOOT: f64 = 0.3048;
+/// Speed in meters per second up to which the pace is shown additionally.
+const MAX_PACE_SPEED_MS: f64 = 3.0;
+
+/// Format the distance `m` in meters as kilometers or miles.
+pub(crate) fn format_distance(m: f64, units: DistanceUnits) -> String {
+    match units {
+        DistanceUnits::Metric => format!("{:.2} km", m / 1000.0),
+        DistanceUnits::Imperial => format!("{:.2} mi", m / METERS_PER_MILE),
+    }
+}
+
+/// Format the elevation difference `m` in meters as meters or feet.
+pub(crate) fn format_elevation(m: f64, units: DistanceUnits) -> String {
+    match units {
+        DistanceUnits::Metric => format!("{m:.0} m"),
+        DistanceUnits::Imperial => format!("{:.0} ft", m / METERS_PER_FOOT),
+    }
+}
+
+/// Format the speed `ms` in meters per second as km/h or mph.
+///
+/// For slow speeds like walking, the pace is appended as in
+/// `4.9 km/h (12:14 min/km)`.
+pub(crate) fn format_speed(ms: f64, units: DistanceUnits) -> String {
+    let (unit_m, speed_unit, distance_unit) = match units {
+        DistanceUnits::Metric => (1000.0, "km/h", "km"),
+        DistanceUnits::Imperial => (METERS_PER_MILE, "mph", "mi"),
+    };
+    let speed = format!("{:.1} {speed_unit}", ms * 3600.0 / unit_m);
+    if ms <= 0.0 || ms > MAX_PACE_SPEED_MS {
+        return speed;
+    }
+
+    let pace = (unit_m / ms).round() as u64;
+    format!(
+        "{speed} ({}:{:02} min/{distance_unit})",
+        pace / 60,
+        pace % 60
+    )
+}
+
+/// Format the duration `d` like `2h 30m 15s`.
+///
+/// Leading zero components are omitted and seconds are dropped for durations
+/// of a day or longer, e.g., `45s` or `1d 2h 30m`.
+pub(crate) fn format_duration(d: Duration) -> String {
+    let secs = d.as_secs();
+    let (days, hours, minutes, seconds) =
+        (secs / 86_400, secs / 3600 % 24, secs / 60 % 60, secs % 60);
+    if days > 0 {
+        format!("{days}d {hours}h {minutes}m")
+    } else if hours > 0 {
+        format!("{hours}h {minutes}m {seconds}s")
+    } else if minutes > 0 {
+        format!("{minutes}m {seconds}s")
+    } else {
+        format!("{seconds}s")
+    }
+}
+
+/// Format the latitude `lat` with `precision` decimals like `48.8582°N`.
+pub(crate) fn format_lat(lat: f64, precision: usize) -> String {
+    let hemisphere = if lat < 0.0 { 'S' } else { 'N' };
+    format!("{:.precision$}°{hemisphere}", lat.abs())
+}
+
+/// Format the longitude `lon` with `precision` decimals like `2.2945°E`.
+pub(crate) fn format_lon(lon: f64, precision: usize) -> String {
+    let hemisphere = if lon < 0.0 { 'W' } else { 'E' };
+    format!("{:.precision$}°{hemisphere}", lon.abs())
+}
+
+/// Format the position at `lat` and `lon` like `48.8582°N, 2.2945°E`.
+pub(crate) fn format_position((lat, lon): (f64, f64), precision: usize) -> String {
+    format!(
+        "{}, {}",
+        format_lat(lat, precision),
+        format_lon(lon, precision)
+    )
+}
+
+/// Format the `statistics` as pairs of labels and values.
+pub(crate) fn statistics_rows(
+    statistics: &TrackStatistics,
+    options: &ConversionOptions,
+) -> Vec<(&'static str, String)> {
+    let units = options.units;
+    let precision = options.coordinate_display_precision;
+    let mut rows = vec![];
+    if let Some(start) = statistics.start {
+        rows.push(("Start", format_position(start, precision)));
+    }
+    if let Some(end) = statistics.end {
+        rows.push(("End", format_position(end, precision)));
+    }
+    rows.push(("Length", format_distance(statistics.length_m, units)));
+    if let Some(duration) = statistics.duration {
+        rows.push(("Duration", format_duration(duration)));
+    }
+    if let Some(speed) = statistics.average_speed_ms() {
+        rows.push(("Average speed", format_speed(speed, units)));
+    }
+    if let Some(gain) = statistics.elevation_gain_m {
+        rows.push(("Elevation gain", format_elevation(gain, units)));
+    }
+    if let Some(loss) = statistics.elevation_loss_m {
+        rows.push(("Elevation loss", format_elevation(loss, units)));
+    }
+    rows
+}
+
+/// Format the `statistics` as lines for a description.
+pub(crate) fn format_statistics(
+    statistics: &TrackStatistics,
+    options: &ConversionOptions,
+) -> String {
+    let mut text = String::new();
+    for (label, value) in statistics_rows(statistics, options) {
+        writeln!(text, "{label}: {value}").unwrap();
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn units() {
+        assert_eq!(format_distance(12_345.0, DistanceUnits::Metric), "12.35 km");
+        assert_eq!(
+            format_distance(12_345.0, DistanceUnits::Imperial),
+            "7.67 mi"
+        );
+        assert_eq!(format_elevation(345.4, DistanceUnits::Metric), "345 m");
+        assert_eq!(format_elevation(345.4, DistanceUnits::Imperial), "1133 ft");
+    }
+
+    #[test]
+    fn speeds() {
+        assert_eq!(format_speed(4.25, DistanceUnits::Metric), "15.3 km/h");
+        assert_eq!(format_speed(4.25, DistanceUnits::Imperial), "9.5 mph");
+        assert_eq!(
+            format_speed(1.3615, DistanceUnits::Metric),
+            "4.9 km/h (12:14 min/km)"
+        );
+        assert_eq!(
+            format_speed(3.0, DistanceUnits::Imperial),
+            "6.7 mph (8:56 min/mi)"
+        );
+        assert_eq!(format_speed(0.0, DistanceUnits::Metric), "0.0 km/h");
+    }
+
+    #[test]
+    fn durations() {
+        let format = |secs| format_duration(Duration::from_secs(secs));
+        assert_eq!(format(0), "0s");
+        assert_eq!(format(45), "45s");
+        assert_eq!(format(5 * 60 + 3), "5m 3s");
+        assert_eq!(format(2 * 3600 + 30 * 60 + 15), "2h 30m 15s");
+        assert_eq!(format(3600), "1h 0m 0s");
+        assert_eq!(format(26 * 3600 + 30 * 60 + 59), "1d 2h 30m");
+    }
+
+    #[test]
+    fn coordinates() {
+        assert_eq!(format_lat(48.858222, 4), "48.8582°N");
+        assert_eq!(format_lat(-33.856784, 2), "33.86°S");
+        assert_eq!(format_lon(2.2945, 4), "2.2945°E");
+        assert_eq!(format_lon(-73.985428, 0), "74°W");
+        assert_eq!(format_position((0.0, 0.0), 1), "0.0°N, 0.0°E");
+    }
+}
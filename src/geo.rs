@@ -0,0 +1,400 @@
+// Copyright 2023 Viktor Reusch
+//
+// This file is part of gpx_kml_convert.
+//
+// gpx_kml_convert is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, either version 3 of the License, or (at your
+// option) any later version.
+//
+// gpx_kml_convert is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License
+// for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with gpx_kml_convert. If not, see <https://www.gnu.org/licenses/>.
+
+//! Geographic computations on GPX data.
+
+use std::{mem, time::Duration};
+
+use gpx::Waypoint;
+use time::OffsetDateTime;
+
+/// Mean radius of the earth in meters.
+const EARTH_RADIUS_M: f64 = 6_371_000.0;
+
+/// Great-circle distance between two waypoints in meters.
+///
+/// This uses the haversine formula, which assumes a spherical earth and
+/// ignores elevation.
+pub(crate) fn haversine_m(a: &Waypoint, b: &Waypoint) -> f64 {
+    let (a, b) = (a.point(), b.point());
+    let (lat_a, lat_b) = (a.y().to_radians(), b.y().to_radians());
+    let delta_lat = lat_b - lat_a;
+    let delta_lon = (b.x() - a.x()).to_radians();
+
+    let h = (delta_lat / 2.0).sin().powi(2)
+        + lat_a.cos() * lat_b.cos() * (delta_lon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_M * h.sqrt().asin()
+}
+
+/// Length of the line through all `points` in meters.
+pub(crate) fn total_length_m(points: &[Waypoint]) -> f64 {
+    points.windows(2).map(|w| haversine_m(&w[0], &w[1])).sum()
+}
+
+/// Split the line through `points` into parts of at most `max_m` meters.
+///
+/// The parts are split at the last existing point before the length is
+/// exceeded and share this point, so no gaps arise between them. A single
+/// step longer than `max_m` forms a part on its own.
+pub(crate) fn split_by_length(points: Vec<Waypoint>, max_m: f64) -> Vec<Vec<Waypoint>> {
+    let mut parts = vec![];
+    let mut part: Vec<Waypoint> = vec![];
+    let mut length = 0.0;
+    for point in points {
+        if let Some(last) = part.last() {
+            let distance = haversine_m(last, &point);
+            if length + distance > max_m && part.len() > 1 {
+                let start = last.clone();
+                parts.push(mem::replace(&mut part, vec![start]));
+                length = 0.0;
+            }
+            length += distance;
+        }
+        part.push(point);
+    }
+    parts.push(part);
+    parts
+}
+
+/// Split the line through `points` into ascending and descending parts.
+///
+/// A part ends at its highest or lowest point once the elevation changed by
+/// more than `threshold_m` meters in the opposite direction. Smaller changes
+/// are considered noise. The parts share the points at which they are split.
+/// Points without elevation never cause a split.
+///
+/// Each part is returned with whether it is ascending. A line without
+/// elevation changes above the threshold forms one part, which is
+/// ascending if its last elevation is not below its first.
+pub(crate) fn split_by_elevation(
+    points: Vec<Waypoint>,
+    threshold_m: f64,
+) -> Vec<(bool, Vec<Waypoint>)> {
+    let mut parts = vec![];
+    let mut start = 0;
+    // Direction of the current part and index of its extreme point.
+    let mut direction: Option<(bool, usize)> = None;
+    for (i, elevation) in points.iter().enumerate() {
+        let Some(elevation) = elevation.elevation else {
+            continue;
+        };
+        let Some((ascending, extreme)) = direction else {
+            let first = points[start..].iter().find_map(|p| p.elevation);
+            if let Some(first) = first.filter(|f| (elevation - f).abs() > threshold_m) {
+                direction = Some((elevation > first, i));
+            }
+            continue;
+        };
+
+        let extreme_elevation = points[extreme].elevation.unwrap_or(elevation);
+        let change = elevation - extreme_elevation;
+        if (ascending && change >= 0.0) || (!ascending && change <= 0.0) {
+            direction = Some((ascending, i));
+        } else if change.abs() > threshold_m {
+            parts.push((ascending, points[start..=extreme].to_vec()));
+            start = extreme;
+            direction = Some((!ascending, i));
+        }
+    }
+
+    let ascending = direction.map_or_else(
+        || {
+            let mut elevations = points.iter().filter_map(|p| p.elevation);
+            let first = elevations.next();
+            first.is_none_or(|first| elevations.next_back().unwrap_or(first) >= first)
+        },
+        |(ascending, _)| ascending,
+    );
+    parts.push((ascending, points[start..].to_vec()));
+    parts
+}
+
+/// Remove the `points` within `radius_m` meters of the start and the end of
+/// the line.
+///
+/// The straight-line distance is used instead of the distance along the line,
+/// so the remaining ends are guaranteed to be outside of the radius even if the
+/// line winds around the start or the end.
+pub(crate) fn trim_privacy_zone(points: Vec<Waypoint>, radius_m: f64) -> Vec<Waypoint> {
+    let (Some(start), Some(end)) = (points.first().cloned(), points.last().cloned()) else {
+        return points;
+    };
+    let Some(first) = points
+        .iter()
+        .position(|p| haversine_m(&start, p) >= radius_m)
+    else {
+        return vec![];
+    };
+    let last = points
+        .iter()
+        .rposition(|p| haversine_m(&end, p) >= radius_m)
+        .unwrap_or(0);
+    if first > last {
+        return vec![];
+    }
+
+    points.into_iter().take(last + 1).skip(first).collect()
+}
+
+/// Infer the activity type of a track from its average `speed_ms` in meters
+/// per second.
+pub(crate) fn infer_activity(speed_ms: f64) -> &'static str {
+    match speed_ms * 3.6 {
+        kmh if kmh < 2.0 => "stopped",
+        kmh if kmh < 7.0 => "hiking",
+        kmh if kmh < 15.0 => "running",
+        kmh if kmh < 40.0 => "cycling",
+        _ => "motorized",
+    }
+}
+
+/// Statistics of a route or track.
+///
+/// All values are stored in SI units.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct TrackStatistics {
+    /// Length in meters.
+    pub(crate) length_m: f64,
+    /// Accumulated ascent in meters if elevation data is available.
+    pub(crate) elevation_gain_m: Option<f64>,
+    /// Accumulated descent in meters if elevation data is available.
+    pub(crate) elevation_loss_m: Option<f64>,
+    /// Latitude and longitude of the first point.
+    pub(crate) start: Option<(f64, f64)>,
+    /// Latitude and longitude of the last point.
+    pub(crate) end: Option<(f64, f64)>,
+    /// Time between the first and the last timestamp if there are any.
+    pub(crate) duration: Option<Duration>,
+}
+
+impl TrackStatistics {
+    /// Compute the statistics of a line consisting of `segments`.
+    ///
+    /// The gaps between segments do not contribute to the statistics.
+    pub(crate) fn new<'a>(segments: impl IntoIterator<Item = &'a [Waypoint]>) -> Self {
+        let mut statistics = Self::default();
+        let mut first_time = None;
+        let mut last_time = None;
+        for points in segments {
+            let mut times = points
+                .iter()
+                .filter_map(|p| p.time)
+                .map(OffsetDateTime::from);
+            if let Some(time) = times.next() {
+                first_time.get_or_insert(time);
+                last_time = Some(times.next_back().unwrap_or(time));
+            }
+
+            statistics.length_m += total_length_m(points);
+            if let (Some(first), Some(last)) = (points.first(), points.last()) {
+                let (first, last) = (first.point(), last.point());
+                statistics.start.get_or_insert((first.y(), first.x()));
+                statistics.end = Some((last.y(), last.x()));
+            }
+
+            let mut elevations = points.iter().filter_map(|p| p.elevation);
+            let Some(mut last) = elevations.next() else {
+                continue;
+            };
+            let gain = statistics.elevation_gain_m.get_or_insert(0.0);
+            let mut loss = 0.0;
+            for elevation in elevations {
+                let delta = elevation - last;
+                if delta > 0.0 {
+                    *gain += delta;
+                } else {
+                    loss -= delta;
+                }
+                last = elevation;
+            }
+            *statistics.elevation_loss_m.get_or_insert(0.0) += loss;
+        }
+        if let (Some(first), Some(last)) = (first_time, last_time) {
+            // Timestamps going backwards result in no duration.
+            statistics.duration = (last - first).try_into().ok();
+        }
+        statistics
+    }
+
+    /// Average speed in meters per second if the duration is known and not
+    /// zero.
+    pub(crate) fn average_speed_ms(&self) -> Option<f64> {
+        self.duration
+            .map(|d| d.as_secs_f64())
+            .filter(|&secs| secs > 0.0)
+            .map(|secs| self.length_m / secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::waypoint;
+
+    #[test]
+    fn haversine() {
+        let eiffel_tower = waypoint(48.858222, 2.2945);
+        let colosseum = waypoint(41.89021, 12.492231);
+        let distance = haversine_m(&eiffel_tower, &colosseum);
+        assert!((distance - 1_109_400.0).abs() < 100.0, "{distance}");
+        assert_eq!(haversine_m(&colosseum, &colosseum), 0.0);
+    }
+
+    #[test]
+    fn split() {
+        // Steps of about 1112 m along the equator.
+        let points: Vec<_> = (0..6).map(|i| waypoint(0.0, f64::from(i) * 0.01)).collect();
+        let lons = |parts: Vec<Vec<Waypoint>>| -> Vec<Vec<f64>> {
+            parts
+                .iter()
+                .map(|p| p.iter().map(|w| (w.point().x() * 100.0).round()).collect())
+                .collect()
+        };
+
+        assert_eq!(
+            lons(split_by_length(points.clone(), 2_500.0)),
+            [vec![0.0, 1.0, 2.0], vec![2.0, 3.0, 4.0], vec![4.0, 5.0]]
+        );
+        assert_eq!(lons(split_by_length(points.clone(), 500.0)).len(), 5);
+        assert_eq!(lons(split_by_length(points, 10_000.0)).len(), 1);
+        assert_eq!(split_by_length(vec![], 500.0), [vec![]]);
+    }
+
+    #[test]
+    fn split_elevation() {
+        let points: Vec<_> = [100.0, 95.0, 130.0, 125.0, 140.0, 80.0, 85.0]
+            .into_iter()
+            .enumerate()
+            .map(|(i, elevation)| {
+                let mut point = waypoint(0.0, i as f64 * 0.01);
+                point.elevation = Some(elevation);
+                point
+            })
+            .collect();
+        let elevations = |parts: Vec<(bool, Vec<Waypoint>)>| -> Vec<(bool, Vec<f64>)> {
+            parts
+                .into_iter()
+                .map(|(a, p)| (a, p.iter().filter_map(|w| w.elevation).collect()))
+                .collect()
+        };
+
+        assert_eq!(
+            elevations(split_by_elevation(points.clone(), 10.0)),
+            [
+                (true, vec![100.0, 95.0, 130.0, 125.0, 140.0]),
+                (false, vec![140.0, 80.0, 85.0])
+            ]
+        );
+        assert_eq!(
+            elevations(split_by_elevation(points.clone(), 4.0)),
+            [
+                (false, vec![100.0, 95.0]),
+                (true, vec![95.0, 130.0]),
+                (false, vec![130.0, 125.0]),
+                (true, vec![125.0, 140.0]),
+                (false, vec![140.0, 80.0]),
+                (true, vec![80.0, 85.0])
+            ]
+        );
+        assert_eq!(split_by_elevation(points.clone(), 100.0).len(), 1);
+        assert!(!split_by_elevation(points[4..].to_vec(), 100.0)[0].0);
+
+        let flat = vec![waypoint(0.0, 0.0), waypoint(0.0, 0.01)];
+        assert_eq!(split_by_elevation(flat.clone(), 10.0), [(true, flat)]);
+    }
+
+    #[test]
+    fn privacy_zone() {
+        // Steps of about 1112 m along the equator.
+        let points: Vec<_> = (0..6).map(|i| waypoint(0.0, f64::from(i) * 0.01)).collect();
+        let lons = |points: Vec<Waypoint>| -> Vec<f64> {
+            points
+                .iter()
+                .map(|w| (w.point().x() * 100.0).round())
+                .collect()
+        };
+
+        assert_eq!(
+            lons(trim_privacy_zone(points.clone(), 1_000.0)),
+            [1.0, 2.0, 3.0, 4.0]
+        );
+        assert_eq!(lons(trim_privacy_zone(points.clone(), 2_000.0)), [2.0, 3.0]);
+        assert!(trim_privacy_zone(points.clone(), 3_000.0).is_empty());
+        assert!(trim_privacy_zone(points[..1].to_vec(), 10.0).is_empty());
+        assert!(trim_privacy_zone(vec![], 10.0).is_empty());
+
+        // Only the ends are trimmed, even if the line returns into the radius.
+        let winding = vec![
+            waypoint(0.0, 0.0),
+            waypoint(0.0, 0.02),
+            waypoint(0.0, 0.001),
+            waypoint(0.0, 0.03),
+            waypoint(0.0, 0.05),
+        ];
+        assert_eq!(lons(trim_privacy_zone(winding, 1_000.0)), [2.0, 0.0, 3.0]);
+    }
+
+    #[test]
+    fn activities() {
+        let activity = |kmh: f64| infer_activity(kmh / 3.6);
+        assert_eq!(activity(0.0), "stopped");
+        assert_eq!(activity(4.5), "hiking");
+        assert_eq!(activity(7.0), "running");
+        assert_eq!(activity(25.0), "cycling");
+        assert_eq!(activity(40.0), "motorized");
+    }
+
+    #[test]
+    fn statistics() {
+        let mut points: Vec<_> = (0..4).map(|i| waypoint(0.0, f64::from(i) * 0.01)).collect();
+        for (point, elevation) in points.iter_mut().zip([100.0, 150.0, 120.0, 130.0]) {
+            point.elevation = Some(elevation);
+        }
+        let flat = [waypoint(1.0, 0.0), waypoint(1.0, 0.01)];
+
+        let statistics = TrackStatistics::new([points.as_slice(), flat.as_slice()]);
+        assert!((statistics.length_m - 4_447.8).abs() < 1.0);
+        assert_eq!(statistics.elevation_gain_m, Some(60.0));
+        assert_eq!(statistics.elevation_loss_m, Some(30.0));
+        assert_eq!(statistics.start, Some((0.0, 0.0)));
+        assert_eq!(statistics.end, Some((1.0, 0.01)));
+
+        let statistics = TrackStatistics::new([flat.as_slice()]);
+        assert_eq!(statistics.elevation_gain_m, None);
+        assert_eq!(statistics.elevation_loss_m, None);
+        assert_eq!(statistics.duration, None);
+    }
+
+    #[test]
+    fn duration() {
+        let mut points: Vec<_> = (0..3).map(|i| waypoint(0.0, f64::from(i) * 0.01)).collect();
+        let start = OffsetDateTime::UNIX_EPOCH;
+        points[0].time = Some(start.into());
+        points[2].time = Some((start + Duration::from_secs(90)).into());
+        let later = [waypoint(1.0, 0.0), waypoint(1.0, 0.01)];
+
+        let statistics = TrackStatistics::new([points.as_slice(), later.as_slice()]);
+        assert_eq!(statistics.duration, Some(Duration::from_secs(90)));
+        let speed = statistics.average_speed_ms().unwrap();
+        assert!((speed - statistics.length_m / 90.0).abs() < 1e-9);
+
+        points.swap(0, 2);
+        let statistics = TrackStatistics::new([points.as_slice()]);
+        assert_eq!(statistics.duration, None);
+        assert_eq!(statistics.average_speed_ms(), None);
+    }
+}
@@ -0,0 +1,122 @@
+// Copyright 2023 Viktor Reusch
+//
+// This file is part of gpx_kml_convert.
+//
+// gpx_kml_convert is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, either version 3 of the License, or (at your
+// option) any later version.
+//
+// gpx_kml_convert is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License
+// for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with gpx_kml_convert. If not, see <https://www.gnu.org/licenses/>.
+
+//! Rendering of descriptions as HTML for the balloons of KML viewers.
+
+use std::fmt::Write;
+
+/// Inline style of the cards rendered by [`card`].
+const CARD_STYLE: &str =
+    "font-family:sans-serif;max-width:320px;padding:8px;border:1px solid #ccc;border-radius:6px";
+
+/// Escape the characters of `text` which are special in HTML.
+pub(crate) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Render an HTML link to `href` with the `text`.
+///
+/// Both are escaped.
+pub(crate) fn link(href: &str, text: &str) -> String {
+    format!(r#"<a href="{}">{}</a>"#, escape(href), escape(text))
+}
+
+/// Render `rows` of labels and HTML values as a table.
+///
+/// Nothing is rendered if there are no `rows`.
+pub(crate) fn table(rows: &[(&str, String)]) -> String {
+    if rows.is_empty() {
+        return String::new();
+    }
+
+    let mut html = "<table>".to_string();
+    for (label, value) in rows {
+        write!(
+            html,
+            r#"<tr><th align="left">{}</th><td>{value}</td></tr>"#,
+            escape(label)
+        )
+        .unwrap();
+    }
+    html.push_str("</table>");
+    html
+}
+
+/// Render a card with the `heading`, the HTML `paragraphs`, and a table of
+/// `rows`.
+///
+/// The heading is escaped. Nothing is rendered if there is no content at all.
+pub(crate) fn card(
+    heading: Option<&str>,
+    paragraphs: &[String],
+    rows: &[(&str, String)],
+) -> String {
+    if heading.is_none() && paragraphs.is_empty() && rows.is_empty() {
+        return String::new();
+    }
+
+    let mut html = format!(r#"<div style="{CARD_STYLE}">"#);
+    if let Some(heading) = heading {
+        write!(html, r#"<h3 style="margin-top:0">{}</h3>"#, escape(heading)).unwrap();
+    }
+    for paragraph in paragraphs {
+        write!(html, "<p>{paragraph}</p>").unwrap();
+    }
+    html.push_str(&table(rows));
+    html.push_str("</div>");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escaping() {
+        assert_eq!(
+            escape(r#"<a href="x">&</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;&amp;&lt;/a&gt;"
+        );
+        assert_eq!(
+            link("https://example.org/?a=1&b=2", "Example"),
+            r#"<a href="https://example.org/?a=1&amp;b=2">Example</a>"#
+        );
+    }
+
+    #[test]
+    fn tables() {
+        assert_eq!(table(&[]), "");
+        assert_eq!(card(None, &[], &[]), "");
+        assert_eq!(
+            table(&[("Type", "Hiking".to_string())]),
+            r#"<table><tr><th align="left">Type</th><td>Hiking</td></tr></table>"#
+        );
+        let html = card(Some("A & B"), &["<b>Nice</b>".to_string()], &[]);
+        assert!(html.contains("<h3 style=\"margin-top:0\">A &amp; B</h3><p><b>Nice</b></p></div>"));
+        assert!(!html.contains("<table>"));
+    }
+}
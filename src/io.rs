@@ -0,0 +1,159 @@
+// Copyright 2023 Viktor Reusch
+//
+// This file is part of gpx_kml_convert.
+//
+// gpx_kml_convert is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, either version 3 of the License, or (at your
+// option) any later version.
+//
+// gpx_kml_convert is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License
+// for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with gpx_kml_convert. If not, see <https://www.gnu.org/licenses/>.
+
+//! Helpers for reading the GPX input and writing the KML output.
+
+use std::io::{self, Read, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Writer limiting the rate at which data is written to the inner writer.
+///
+/// This is useful for streaming large KML files over a network without
+/// overwhelming the receiver. The writer sleeps whenever it is ahead of the
+/// target rate, measured from the first write.
+///
+/// This relies on [`Instant`] and [`thread::sleep`], which are not available
+/// on `wasm32-unknown-unknown`.
+///
+/// # Example
+/// ```no_run
+/// # use std::{fs::File, net::TcpStream};
+/// # use gpx_kml_convert::{convert_with_options, io::ThrottledWriter, ConversionOptions};
+/// #
+/// let source = File::open("track.gpx").expect("file not found");
+/// let stream = TcpStream::connect("127.0.0.1:8080").expect("connection failed");
+///
+/// // Limit the output to 1 MiB/s.
+/// let sink = ThrottledWriter::new(stream, 1024 * 1024);
+/// let _ = convert_with_options(source, sink, &ConversionOptions::default())
+///     .expect("conversion failed");
+/// ```
+#[derive(Debug)]
+pub struct ThrottledWriter<W> {
+    inner: W,
+    bytes_per_second: usize,
+    start: Option<Instant>,
+    written: u64,
+}
+
+impl<W: Write> ThrottledWriter<W> {
+    /// Wrap `inner` to write at most `bytes_per_second` on average.
+    ///
+    /// A rate of zero disables the throttling.
+    pub fn new(inner: W, bytes_per_second: usize) -> Self {
+        Self {
+            inner,
+            bytes_per_second,
+            start: None,
+            written: 0,
+        }
+    }
+
+    /// Get a reference to the inner writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Unwrap the inner writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for ThrottledWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.bytes_per_second == 0 {
+            return self.inner.write(buf);
+        }
+
+        let start = *self.start.get_or_insert_with(Instant::now);
+        // Write at most one second worth of data at once so that large buffers
+        // are spread evenly.
+        let len = buf.len().min(self.bytes_per_second);
+        let written = self.inner.write(&buf[..len])?;
+        self.written += written as u64;
+
+        let schedule = Duration::from_secs_f64(self.written as f64 / self.bytes_per_second as f64);
+        if let Some(ahead) = schedule.checked_sub(start.elapsed()) {
+            thread::sleep(ahead);
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Reader computing the CRC32 checksum of all data read from it.
+pub(crate) struct ChecksumReader<R> {
+    inner: R,
+    hasher: crc32fast::Hasher,
+}
+
+impl<R: Read> ChecksumReader<R> {
+    /// Wrap `inner` to compute its checksum.
+    pub(crate) fn new(inner: R) -> Self {
+        Self {
+            inner,
+            hasher: crc32fast::Hasher::new(),
+        }
+    }
+
+    /// Read the remaining data and return the checksum of all data.
+    pub(crate) fn finish(mut self) -> io::Result<u32> {
+        io::copy(&mut self, &mut io::sink())?;
+        Ok(self.hasher.finalize())
+    }
+}
+
+impl<R: Read> Read for ChecksumReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.hasher.update(&buf[..read]);
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn throttled() {
+        let data = [42; 300];
+        let mut writer = ThrottledWriter::new(vec![], 1000);
+        let start = Instant::now();
+        writer.write_all(&data).unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(250));
+        assert_eq!(writer.get_ref().as_slice(), data);
+
+        let mut writer = ThrottledWriter::new(vec![], 0);
+        writer.write_all(&data).unwrap();
+        assert_eq!(writer.into_inner(), data);
+    }
+
+    #[test]
+    fn checksum() {
+        let mut reader = ChecksumReader::new(&b"123456789"[..]);
+        let mut start = [0; 4];
+        reader.read_exact(&mut start).unwrap();
+        // The unread data is included, too.
+        assert_eq!(reader.finish().unwrap(), 0xcbf4_3926);
+    }
+}
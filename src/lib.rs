@@ -21,31 +21,135 @@
 //! It reads in GPX waypoints, routes, and tours and converts them to KML for
 //! visualization.
 //!
-//! See [`convert`] for information on how to use this library.
+//! See [`convert`] for information on how to use this library. The conversion
+//! can be customized with [`convert_with_options`]. The most commonly used
+//! items can be imported at once from the [`prelude`].
+//!
+//! # Features
+//!
+//! - `logging`: Log the progress of conversions and skipped data via the
+//!   [`log`](https://docs.rs/log) crate.
+//! - `slog`: Provide [`convert_with_logger`] for structured logging via the
+//!   [`slog`](https://docs.rs/slog) crate.
+//! - `ammonia`: Provide [`DescriptionEscape::SanitizeHtml`] for sanitizing
+//!   HTML in descriptions via the [`ammonia`](https://docs.rs/ammonia) crate.
+//! - `uuid`: Provide [`convert_with_id`] for identifying conversions via the
+//!   [`uuid`](https://docs.rs/uuid) crate.
+//! - `indicatif`: Provide [`ProgressStyle::ProgressBar`] for displaying a
+//!   progress bar via the [`indicatif`](https://docs.rs/indicatif) crate.
+
+#[macro_use]
+mod logging;
+mod atom;
+mod clock;
+mod diff;
+mod env;
+mod format;
+mod geo;
+mod html;
+pub mod io;
+mod osmand;
+mod progress;
+mod reverse;
+#[cfg(test)]
+mod test_helpers;
+mod util;
+mod xml;
+
+/// Commonly used items for glob imports.
+///
+/// ```
+/// use gpx_kml_convert::prelude::*;
+///
+/// let options = ConversionOptions {
+///     units: DistanceUnits::Imperial,
+///     ..Default::default()
+/// };
+/// ```
+pub mod prelude {
+    pub use crate::{
+        convert, BoundingBox, ConversionOptions, ConversionReport, DistanceUnits, Error, Warning,
+    };
+}
 
 use std::collections::HashMap;
-use std::fmt::Write;
-use std::io::{self, Read};
+use std::fmt::{self, Display, Formatter, Write};
+use std::io::Read;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::{mem, ptr};
 
-use gpx::{errors::GpxError, Link, Metadata, Route, Track, TrackSegment, Waypoint};
-use kml::types::{AltitudeMode, Coord, Geometry, LineString, MultiGeometry, Placemark, Point};
+use atom::AtomFeed;
+use clock::Stopwatch;
+pub use diff::{diff_gpx_kml, DiffItem};
+pub use env::EnvConfigError;
+use format::{
+    format_distance, format_elevation, format_position, format_statistics, statistics_rows,
+};
+use geo::{
+    haversine_m, infer_activity, split_by_elevation, split_by_length, total_length_m,
+    trim_privacy_zone, TrackStatistics,
+};
+use gpx::{
+    errors::GpxError, Gpx, GpxVersion, Link, Metadata, Route, Time, Track, TrackSegment, Waypoint,
+};
+use io::ChecksumReader;
+use kml::types::{
+    AltitudeMode, Coord, CoordType, Geometry, LineString, MultiGeometry, Placemark, Point,
+};
 use kml::{types::Element, Kml, KmlDocument, KmlVersion, KmlWriter};
+use osmand::{OSMAND_NAMESPACE, OSMAND_URI};
+use progress::Progress;
+pub use progress::{ProgressReporter, ProgressStyle};
+pub use reverse::{kml_to_gpx_structs, validate_kml_roundtrip, RoundtripReport};
 use thiserror::Error;
+use util::{normalize_activity_type, strip_html};
 
 /// This line needs to be prepended to the KML output.
 const XML_HEAD: &str = r#"<?xml version="1.0" encoding="UTF-8"?>"#;
 /// Namespace attributes for the `<kml>` tag.
 const NAMESPACES: &[(&str, &str)] = &[
     ("xmlns", "http://www.opengis.net/kml/2.2"),
-    ("xmlns:atom", "http://www.w3.org/2005/Atom"),
+    (ATOM_NAMESPACE, ATOM_URI),
 ];
+/// Namespace attribute for Atom elements.
+const ATOM_NAMESPACE: &str = "xmlns:atom";
+/// URI of the Atom namespace.
+const ATOM_URI: &str = "http://www.w3.org/2005/Atom";
 /// Default value for the open attribute of the main KML _Document_.
 const DEFAULT_OPEN: &str = "1";
 /// Default value for tessellating lines in KML.
 const DEFAULT_TESSELLATE: bool = true;
+/// Default number of decimals for coordinates in descriptions.
+const DEFAULT_COORDINATE_DISPLAY_PRECISION: usize = 4;
+/// Default zoom level of OpenStreetMap links.
+const DEFAULT_OSM_ZOOM: u8 = 15;
+/// Default maximum distance between the start and the end of a loop.
+const DEFAULT_LOOP_THRESHOLD_M: f64 = 100.0;
+/// Maximum number of intermediate waypoints of Google Maps directions.
+const MAX_DIRECTIONS_WAYPOINTS: usize = 8;
+/// Maximum number of characters of a _Snippet_.
+const SNIPPET_MAX_CHARS: usize = 100;
+/// Number of lines displayed of a _Snippet_.
+const SNIPPET_MAX_LINES: &str = "2";
 
-/// Use double precision for coordinate values.
-type CoordValue = f64;
+/// Number type of the coordinates in the KML output.
+trait CoordValue: CoordType + FromStr + Default + Display {
+    /// Convert the GPX coordinate `value`, possibly losing precision.
+    fn from_f64(value: f64) -> Self;
+}
+
+impl CoordValue for f64 {
+    fn from_f64(value: f64) -> Self {
+        value
+    }
+}
+
+impl CoordValue for f32 {
+    fn from_f64(value: f64) -> Self {
+        value as f32
+    }
+}
 
 /// Error returned from the [`convert`] function.
 #[derive(Error, Debug)]
@@ -56,6 +160,923 @@ pub enum Error {
     /// KML writing failed.
     #[error("writing KML failed: {0}")]
     Kml(#[from] kml::Error),
+    /// The GPX data contains no waypoints, routes, or tracks.
+    ///
+    /// This is only returned if [`ConversionOptions::require_content`] is set.
+    #[error("GPX contains no waypoints, routes, or tracks")]
+    EmptyInput,
+    /// Reading the input failed outside of the GPX parser or writing the
+    /// output failed outside of the KML writer.
+    ///
+    /// This can occur while computing the checksum for
+    /// [`ConversionOptions::add_checksum`], while forcing the
+    /// [`ConversionOptions::input_format`], or while writing the XML header
+    /// and comments. Malformed XML is reported with
+    /// [`std::io::ErrorKind::InvalidData`] when forcing the input format.
+    #[error("I/O failed: {0}")]
+    Io(#[from] std::io::Error),
+    /// Post-processing the written KML data failed.
+    ///
+    /// This can occur while wrapping descriptions in CDATA sections, e.g., for
+    /// [`ConversionOptions::description_format`], or while indenting the
+    /// output of [`convert_to_pretty_kml`].
+    #[error("processing KML failed: {0}")]
+    Xml(String),
+    /// The KML data cannot be converted back to GPX.
+    ///
+    /// This is returned from [`kml_to_gpx_structs`] and
+    /// [`validate_kml_roundtrip`].
+    #[error("invalid KML: {0}")]
+    InvalidKml(String),
+}
+
+/// Options for customizing the conversion with [`convert_with_options`].
+///
+/// The [`Default`] options result in the same output as [`convert`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConversionOptions {
+    /// Whether lines should follow the terrain when they are clamped to the
+    /// ground.
+    ///
+    /// Defaults to `true`.
+    pub tessellate: bool,
+    /// Altitude mode used for all geometries.
+    ///
+    /// Defaults to `None`, which selects [`AltitudeMode::Absolute`] for
+    /// geometries with elevation data and [`AltitudeMode::ClampToGround`]
+    /// otherwise.
+    pub altitude_mode: Option<AltitudeMode>,
+    /// Line colors for routes and tracks keyed by their GPX _type_.
+    ///
+    /// The colors use the KML notation `aabbggrr`, e.g., `ff0000ff` for
+    /// opaque red. The keys are compared case-insensitively and hyphens or
+    /// underscores match spaces, so `"Trail Running"` also applies to
+    /// `trail-running`.
+    ///
+    /// Defaults to an empty map, which leaves the colors to the KML viewer.
+    pub activity_colors: HashMap<String, String>,
+    /// Whether to emit elements of the
+    /// [Atom schema](https://www.w3.org/2005/Atom) for authors and links.
+    ///
+    /// Some KML consumers reject these elements. If `false`, neither the
+    /// elements nor the `xmlns:atom` namespace are written.
+    ///
+    /// Defaults to `true`.
+    pub include_atom_elements: bool,
+    /// Only convert points inside this bounding box.
+    ///
+    /// Waypoints outside of the box are skipped and route and track points
+    /// outside of the box are removed from their lines.
+    ///
+    /// Defaults to `None`, which converts all points.
+    pub bbox_filter: Option<BoundingBox>,
+    /// Whether to add statistics like start and end, length, and elevation
+    /// gain to the descriptions of routes and tracks.
+    ///
+    /// Defaults to `false`.
+    pub include_statistics: bool,
+    /// Units for displaying statistics.
+    ///
+    /// Defaults to [`DistanceUnits::Metric`].
+    pub units: DistanceUnits,
+    /// Number of decimals for coordinates in descriptions.
+    ///
+    /// This does not affect the precision of the KML coordinates.
+    ///
+    /// Defaults to `4`.
+    pub coordinate_display_precision: usize,
+    /// Whether to add the GPX metadata time as _TimeStamp_ to the KML
+    /// document.
+    ///
+    /// This allows applications to sort KML documents by the time of their
+    /// recording. The time is still mentioned in the document description.
+    ///
+    /// Defaults to `false`.
+    pub metadata_time_as_timestamp: bool,
+    /// Maximum length of the track segments in kilometers.
+    ///
+    /// Longer segments are split into several _LineStrings_ at existing
+    /// points, which is useful for applications limiting the length of lines.
+    /// Routes are not split.
+    ///
+    /// Defaults to `None`, which keeps the segments as they are.
+    pub max_segment_length_km: Option<f64>,
+    /// Whether to start the descriptions of routes and tracks with their GPX
+    /// _number_, e.g., `Route #2` or `Track #3`.
+    ///
+    /// This helps to correlate placemarks with the routes and tracks of the
+    /// GPX file.
+    ///
+    /// Defaults to `false`.
+    pub include_track_number: bool,
+    /// Whether to fail with [`Error::EmptyInput`] instead of writing an empty
+    /// KML document if the GPX data contains no waypoints, routes, or tracks.
+    ///
+    /// Defaults to `false`.
+    pub require_content: bool,
+    /// Whether to wrap the KML output in an `<atom:feed>`, e.g., for sharing
+    /// tracks via a feed.
+    ///
+    /// The title, ID, and update time of the feed are derived from the GPX
+    /// metadata. The ID is taken from the first metadata link or otherwise
+    /// generated from the title and time. If the GPX data contains no time,
+    /// the Unix epoch is used as update time.
+    ///
+    /// Defaults to `false`.
+    pub atom_feed_mode: bool,
+    /// Treatment of markup in descriptions.
+    ///
+    /// Defaults to [`DescriptionEscape::None`].
+    pub description_escape: DescriptionEscape,
+    /// Whether to omit elevations of exactly zero from the coordinates.
+    ///
+    /// Some tools interpret a zero altitude as sea level instead of a missing
+    /// value. Without elevation data, geometries are clamped to the ground
+    /// unless [`altitude_mode`](Self::altitude_mode) is set.
+    ///
+    /// Defaults to `false`.
+    pub omit_zero_altitude: bool,
+    /// Number type of the KML coordinates.
+    ///
+    /// Defaults to [`CoordinateType::F64`].
+    pub coordinate_type: CoordinateType,
+    /// Whether to add the CRC32 checksum of the GPX input as comment like
+    /// `<!-- GPX-CRC32: 1A2B3C4D -->` after the XML declaration.
+    ///
+    /// This allows verifying that a KML file was generated from a specific GPX
+    /// file. The checksum is only computed by functions reading the GPX input
+    /// themselves, i.e., not by [`convert_gpx`]. For multiple documents, one
+    /// comment per source is added.
+    ///
+    /// Note that the reader of the `kml` crate fails on XML comments.
+    ///
+    /// Defaults to `false`.
+    pub add_checksum: bool,
+    /// Way of reporting the conversion progress.
+    ///
+    /// Defaults to [`ProgressStyle::None`].
+    pub progress: ProgressStyle,
+    /// Rendering of the placemark descriptions, which KML viewers display in
+    /// balloons.
+    ///
+    /// The HTML formats always wrap descriptions in CDATA sections like
+    /// [`DescriptionEscape::HtmlCdata`]. Descriptions and comments from the
+    /// GPX data are treated according to
+    /// [`description_escape`](Self::description_escape), while all other
+    /// values are escaped.
+    ///
+    /// Defaults to [`DescriptionFormat::PlainText`].
+    pub description_format: DescriptionFormat,
+    /// GPX version for parsing the input.
+    ///
+    /// Some legacy files declare a wrong or unsupported version. Forcing a
+    /// version replaces the `version` attribute of the input before parsing.
+    /// This has no effect on [`convert_gpx`], which takes parsed GPX data,
+    /// but applies to [`parse_gpx`].
+    ///
+    /// Defaults to [`InputFormat::Auto`].
+    pub input_format: InputFormat,
+    /// Name of the KML document replacing the name from the GPX metadata.
+    ///
+    /// This is useful if the name should reflect the context of the
+    /// conversion, e.g., the user or the upload date. In the
+    /// [`atom_feed_mode`](Self::atom_feed_mode), this is the feed title, too.
+    ///
+    /// Defaults to `None`, which uses the GPX metadata name if present.
+    pub name_override: Option<String>,
+    /// Text to start every placemark description with, e.g., a copyright
+    /// notice.
+    ///
+    /// The prefix is separated from the description by a newline. It is not
+    /// escaped, so it may contain HTML.
+    ///
+    /// Defaults to `None`.
+    pub description_prefix: Option<String>,
+    /// Text to end every placemark description with, e.g., a reference to a
+    /// website.
+    ///
+    /// The suffix is separated from the description by a newline. It is not
+    /// escaped, so it may contain HTML.
+    ///
+    /// Defaults to `None`.
+    pub description_suffix: Option<String>,
+    /// Whether tracks are visible when the KML file is opened.
+    ///
+    /// If `false`, the tracks are hidden by `<visibility>0</visibility>` but
+    /// can be shown by the user. This is useful for reference-only data.
+    ///
+    /// Defaults to `true`.
+    pub tracks_visible: bool,
+    /// Whether waypoints are visible when the KML file is opened.
+    ///
+    /// See [`tracks_visible`](Self::tracks_visible).
+    ///
+    /// Defaults to `true`.
+    pub waypoints_visible: bool,
+    /// Whether to add a _Snippet_ to each placemark, which Google Earth shows
+    /// below the name in the sidebar.
+    ///
+    /// The snippet contains the beginning of the GPX description without
+    /// markup. For routes and tracks without description, a summary like
+    /// `120 points, 12.35 km` is used instead.
+    ///
+    /// Defaults to `false`.
+    pub use_snippet: bool,
+    /// Whether to add icon styles for the [OsmAnd](https://osmand.net) app to
+    /// waypoints.
+    ///
+    /// The `<osmand:icon>` is selected by the GPX _sym_ of the waypoint. Common
+    /// symbols like `Summit` or `Parking Area` are mapped to their OsmAnd
+    /// equivalents, while other symbols result in a star.
+    ///
+    /// Defaults to `false`.
+    pub osmand_compatible: bool,
+    /// Whether to append a link to the position on
+    /// [OpenStreetMap](https://www.openstreetmap.org) to waypoint
+    /// descriptions.
+    ///
+    /// As the link is HTML, this wraps all descriptions in CDATA sections like
+    /// [`DescriptionEscape::HtmlCdata`].
+    ///
+    /// Defaults to `false`.
+    pub add_osm_link: bool,
+    /// Zoom level of the map opened by links from
+    /// [`add_osm_link`](Self::add_osm_link).
+    ///
+    /// Defaults to `15`.
+    pub osm_zoom: u8,
+    /// Whether to append a link to directions along the route on
+    /// [Google Maps](https://maps.google.com) to route descriptions.
+    ///
+    /// The first and last route points are used as origin and destination.
+    /// Up to eight evenly spaced intermediate points are added as waypoints.
+    /// As the link is HTML, this wraps all descriptions in CDATA sections like
+    /// [`DescriptionEscape::HtmlCdata`].
+    ///
+    /// Defaults to `false`.
+    pub add_directions_link: bool,
+    /// Minimum elevation change in meters for splitting tracks into ascents
+    /// and descents, e.g., lift rides and ski runs.
+    ///
+    /// Track segments are split where the direction of the elevation change
+    /// reverses by more than this threshold. The ascending and descending
+    /// parts of each track are converted to separate placemarks in the
+    /// folders _Ascents_ and _Descents_. Folders without placemarks are
+    /// omitted. Segments without elevation are converted to placemarks
+    /// outside of both folders.
+    ///
+    /// Defaults to `None`, which keeps the tracks as they are.
+    pub split_on_elevation_change: Option<f64>,
+    /// Whether to mark routes and tracks which end near their start.
+    ///
+    /// The placemarks of loops are tagged with the _ExtendedData_ `is_loop`
+    /// set to `true`. Their names are kept.
+    ///
+    /// Defaults to `false`.
+    pub detect_loops: bool,
+    /// Maximum distance in meters between the start and the end of a loop for
+    /// [`detect_loops`](Self::detect_loops).
+    ///
+    /// Defaults to `100.0`.
+    pub loop_threshold_m: f64,
+    /// Whether to merge tracks with identical names into one track.
+    ///
+    /// The segments of all tracks with the same name are combined in the
+    /// first of these tracks, which keeps its other data. Unnamed tracks are
+    /// not merged. This is useful for GPX data combined from several files,
+    /// e.g., with a [`GpxAccumulator`], which contain recurring track names.
+    /// With [`convert_to_multi_document`], tracks are merged across all
+    /// documents into the document of their first occurrence.
+    ///
+    /// Defaults to `false`.
+    pub merge_tracks_by_name: bool,
+    /// Whether to infer the _type_ of tracks without one from their average
+    /// speed.
+    ///
+    /// The types are `stopped` below 2 km/h, `hiking` below 7 km/h,
+    /// `running` below 15 km/h, `cycling` below 40 km/h, and `motorized`
+    /// otherwise. The inferred types are also used for the
+    /// [`activity_colors`](Self::activity_colors). Tracks without timestamps
+    /// keep no type.
+    ///
+    /// Defaults to `false`.
+    pub infer_activity_from_speed: bool,
+    /// Radius in meters around the start and the end of each track segment
+    /// in which points are trimmed, e.g., to hide the home address.
+    ///
+    /// Only the leading and trailing points within the radius are trimmed, so
+    /// the first and last remaining points are at least this far away from
+    /// the original start and end. Points in between are kept even if the
+    /// segment returns into the radius. Segments within the radius are
+    /// removed entirely. The trimmed points are excluded from the
+    /// [`ConversionReport`], too. Routes and waypoints are not affected.
+    ///
+    /// Defaults to `None`, which keeps all points.
+    pub privacy_radius_m: Option<f64>,
+    /// Areas in which track points are removed, e.g., to redact the inside of
+    /// a building.
+    ///
+    /// Points on the boundary of a box are kept. The points around a removed
+    /// part are connected directly without interpolation. Routes and
+    /// waypoints are not affected.
+    ///
+    /// Defaults to an empty list, which keeps all points.
+    pub geofence_exclusions: Vec<BoundingBox>,
+    /// Maximum age in days of the latest timestamp of a track.
+    ///
+    /// Older tracks are dropped with a [`Warning::TrackDroppedTooOld`].
+    /// Tracks without timestamps are kept. The age is measured from the
+    /// current system time. It is not available on `wasm32-unknown-unknown`,
+    /// where all tracks are kept.
+    ///
+    /// Defaults to `None`, which keeps all tracks.
+    pub max_track_age_days: Option<u32>,
+    /// Minimum length in meters of a track.
+    ///
+    /// Shorter tracks, e.g., GPS drift while standing still, are dropped with
+    /// a [`Warning::TrackDroppedTooShort`]. The length is the sum of the
+    /// lengths of all segments. Routes are filtered with
+    /// [`min_route_length_m`](Self::min_route_length_m) instead.
+    ///
+    /// Defaults to `None`, which keeps all tracks.
+    pub min_track_length_m: Option<f64>,
+    /// Minimum length in meters of a route.
+    ///
+    /// Shorter routes are dropped with a [`Warning::RouteDroppedTooShort`].
+    ///
+    /// Defaults to `None`, which keeps all routes.
+    pub min_route_length_m: Option<f64>,
+    /// Minimum time between the first and the last timestamp of a track.
+    ///
+    /// Shorter tracks are dropped with a [`Warning::TrackDroppedTooBrief`].
+    /// Tracks without timestamps are kept.
+    ///
+    /// Defaults to `None`, which keeps all tracks.
+    pub min_track_duration: Option<Duration>,
+}
+
+impl Default for ConversionOptions {
+    fn default() -> Self {
+        Self {
+            tessellate: DEFAULT_TESSELLATE,
+            altitude_mode: None,
+            activity_colors: HashMap::new(),
+            include_atom_elements: true,
+            bbox_filter: None,
+            include_statistics: false,
+            units: DistanceUnits::default(),
+            coordinate_display_precision: DEFAULT_COORDINATE_DISPLAY_PRECISION,
+            metadata_time_as_timestamp: false,
+            max_segment_length_km: None,
+            include_track_number: false,
+            require_content: false,
+            atom_feed_mode: false,
+            description_escape: DescriptionEscape::default(),
+            omit_zero_altitude: false,
+            coordinate_type: CoordinateType::default(),
+            add_checksum: false,
+            progress: ProgressStyle::default(),
+            description_format: DescriptionFormat::default(),
+            input_format: InputFormat::default(),
+            name_override: None,
+            description_prefix: None,
+            description_suffix: None,
+            tracks_visible: true,
+            waypoints_visible: true,
+            use_snippet: false,
+            osmand_compatible: false,
+            add_osm_link: false,
+            osm_zoom: DEFAULT_OSM_ZOOM,
+            add_directions_link: false,
+            split_on_elevation_change: None,
+            detect_loops: false,
+            loop_threshold_m: DEFAULT_LOOP_THRESHOLD_M,
+            merge_tracks_by_name: false,
+            infer_activity_from_speed: false,
+            privacy_radius_m: None,
+            geofence_exclusions: vec![],
+            max_track_age_days: None,
+            min_track_length_m: None,
+            min_route_length_m: None,
+            min_track_duration: None,
+        }
+    }
+}
+
+/// Units for displaying distances, elevations, and speeds.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DistanceUnits {
+    /// Kilometers and meters.
+    #[default]
+    Metric,
+    /// Miles and feet.
+    Imperial,
+}
+
+/// Number type of the KML coordinates.
+///
+/// This determines how many digits are written for each coordinate value.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CoordinateType {
+    /// Double precision, which keeps the precision of the GPX data.
+    #[default]
+    F64,
+    /// Single precision, which results in smaller KML files.
+    ///
+    /// This is precise to about a meter, which suffices for displaying
+    /// coordinates.
+    F32,
+}
+
+/// Treatment of markup in descriptions.
+///
+/// KML viewers usually render descriptions as HTML. Thus, HTML from GPX
+/// descriptions is displayed as such unless it is removed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+// Variants may depend on enabled features.
+#[non_exhaustive]
+pub enum DescriptionEscape {
+    /// Keep markup and escape it as XML text.
+    #[default]
+    None,
+    /// Keep markup and wrap descriptions in CDATA sections.
+    ///
+    /// The result is the same as with [`DescriptionEscape::None`] for XML
+    /// parsers, but HTML stays readable in the KML file.
+    HtmlCdata,
+    /// Remove all HTML tags.
+    StripHtml,
+    /// Remove potentially unsafe HTML like scripts but keep safe tags.
+    #[cfg(feature = "ammonia")]
+    SanitizeHtml,
+}
+
+/// Rendering of placemark descriptions.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DescriptionFormat {
+    /// One line per value like `Source: Wikipedia`.
+    #[default]
+    PlainText,
+    /// An HTML table with one row per value, e.g., for the name, coordinates,
+    /// elevation, time, source, type, and links.
+    HtmlTable,
+    /// A styled HTML card with the name as heading, the description and
+    /// comment as paragraphs, and a table of the remaining values.
+    HtmlCard,
+}
+
+/// GPX version for parsing the input.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InputFormat {
+    /// Use the version declared by the `version` attribute of the input.
+    #[default]
+    Auto,
+    /// Parse the input as GPX 1.0.
+    Gpx10,
+    /// Parse the input as GPX 1.1.
+    Gpx11,
+}
+
+/// Report about a successful conversion.
+///
+/// This is non-exhaustive as fields depend on the enabled features.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[must_use = "the report should be inspected"]
+#[non_exhaustive]
+pub struct ConversionReport {
+    /// Area spanned by all converted points.
+    pub bounding_box: BoundingBox,
+    /// Time spent parsing the GPX input.
+    ///
+    /// This is only measured by [`convert_with_options`] and is zero
+    /// otherwise.
+    pub parse_duration: Duration,
+    /// Time spent building and writing the KML output.
+    ///
+    /// This is only measured by [`convert_with_options`] and is zero
+    /// otherwise.
+    pub convert_duration: Duration,
+    /// Unique ID of the conversion.
+    ///
+    /// This is only set by [`convert_with_id`].
+    #[cfg(feature = "uuid")]
+    pub id: Option<uuid::Uuid>,
+    /// Number of converted waypoints.
+    pub waypoint_count: usize,
+    /// Number of converted routes.
+    pub route_count: usize,
+    /// Number of converted tracks.
+    ///
+    /// Tracks split by
+    /// [`split_on_elevation_change`](ConversionOptions::split_on_elevation_change)
+    /// count once.
+    pub track_count: usize,
+    /// Notices about GPX data which was dropped by the filters of the
+    /// [`ConversionOptions`].
+    pub warnings: Vec<Warning>,
+}
+
+/// Notice about GPX data dropped during a conversion.
+///
+/// This is collected in [`ConversionReport::warnings`] and additionally
+/// logged.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Warning {
+    /// The latest timestamp of a track is older than
+    /// [`ConversionOptions::max_track_age_days`].
+    TrackDroppedTooOld {
+        /// Name of the track or an empty string.
+        name: String,
+        /// Age of the latest timestamp in days.
+        age_days: f64,
+    },
+    /// A track is shorter than [`ConversionOptions::min_track_length_m`].
+    TrackDroppedTooShort {
+        /// Name of the track or an empty string.
+        name: String,
+        /// Length of the track in meters.
+        length_m: f64,
+    },
+    /// A track lasted less than [`ConversionOptions::min_track_duration`].
+    TrackDroppedTooBrief {
+        /// Name of the track or an empty string.
+        name: String,
+        /// Time between the first and the last timestamp in seconds.
+        duration_s: f64,
+    },
+    /// A route is shorter than [`ConversionOptions::min_route_length_m`].
+    RouteDroppedTooShort {
+        /// Name of the route or an empty string.
+        name: String,
+        /// Length of the route in meters.
+        length_m: f64,
+    },
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TrackDroppedTooOld { name, age_days } => {
+                write!(f, "dropped track {name:?} which is {age_days:.1} days old")
+            }
+            Self::TrackDroppedTooShort { name, length_m } => {
+                write!(
+                    f,
+                    "dropped track {name:?} which is only {length_m:.1} m long"
+                )
+            }
+            Self::TrackDroppedTooBrief { name, duration_s } => {
+                write!(f, "dropped track {name:?} which lasted only {duration_s} s")
+            }
+            Self::RouteDroppedTooShort { name, length_m } => {
+                write!(
+                    f,
+                    "dropped route {name:?} which is only {length_m:.1} m long"
+                )
+            }
+        }
+    }
+}
+
+/// Geographic area spanned by minimum and maximum coordinates in degrees.
+///
+/// The [`Default`] box is empty. Its values are NaN until the first point is
+/// added.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoundingBox {
+    /// Southern boundary.
+    pub min_lat: f64,
+    /// Northern boundary.
+    pub max_lat: f64,
+    /// Western boundary.
+    pub min_lon: f64,
+    /// Eastern boundary.
+    pub max_lon: f64,
+}
+
+impl BoundingBox {
+    /// Whether no point was added to this box yet.
+    pub fn is_empty(&self) -> bool {
+        [self.min_lat, self.max_lat, self.min_lon, self.max_lon]
+            .iter()
+            .any(|v| v.is_nan())
+    }
+
+    /// Whether the point at `lat` and `lon` lies inside this box.
+    ///
+    /// Points on the boundary are inside. An empty box contains no points.
+    pub fn contains(&self, lat: f64, lon: f64) -> bool {
+        (self.min_lat..=self.max_lat).contains(&lat) && (self.min_lon..=self.max_lon).contains(&lon)
+    }
+
+    /// Create the smallest box containing all KML `coords`.
+    ///
+    /// Returns `None` if `coords` is empty.
+    pub fn from_coords(coords: &[Coord<f64>]) -> Option<BoundingBox> {
+        if coords.is_empty() {
+            return None;
+        }
+
+        let mut bbox = Self::default();
+        for coord in coords {
+            bbox.expand(coord.y, coord.x);
+        }
+        Some(bbox)
+    }
+
+    /// Grow this box to include the point at `lat` and `lon`.
+    pub fn expand(&mut self, lat: f64, lon: f64) {
+        // `f64::min` and `f64::max` ignore the NaN values of an empty box.
+        self.min_lat = self.min_lat.min(lat);
+        self.max_lat = self.max_lat.max(lat);
+        self.min_lon = self.min_lon.min(lon);
+        self.max_lon = self.max_lon.max(lon);
+    }
+
+    /// Return the smallest box containing this and the `other` box.
+    pub fn union(&self, other: &BoundingBox) -> BoundingBox {
+        BoundingBox {
+            min_lat: self.min_lat.min(other.min_lat),
+            max_lat: self.max_lat.max(other.max_lat),
+            min_lon: self.min_lon.min(other.min_lon),
+            max_lon: self.max_lon.max(other.max_lon),
+        }
+    }
+}
+
+impl Default for BoundingBox {
+    fn default() -> Self {
+        Self {
+            min_lat: f64::NAN,
+            max_lat: f64::NAN,
+            min_lon: f64::NAN,
+            max_lon: f64::NAN,
+        }
+    }
+}
+
+impl Display for BoundingBox {
+    /// Format the box like `N47.3°–N48.9°, E6.1°–E8.3°`.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return f.write_str("empty");
+        }
+
+        /// Format a coordinate `value` with a hemisphere prefix.
+        fn coord(value: f64, positive: char, negative: char) -> String {
+            let hemisphere = if value < 0.0 { negative } else { positive };
+            format!("{hemisphere}{:.1}°", value.abs())
+        }
+
+        write!(
+            f,
+            "{}–{}, {}–{}",
+            coord(self.min_lat, 'N', 'S'),
+            coord(self.max_lat, 'N', 'S'),
+            coord(self.min_lon, 'E', 'W'),
+            coord(self.max_lon, 'E', 'W'),
+        )
+    }
+}
+
+/// Overview of the contents of GPX data.
+///
+/// This is returned from [`gpx_statistics`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GpxStatistics {
+    /// Number of waypoints.
+    pub waypoint_count: usize,
+    /// Number of routes.
+    pub route_count: usize,
+    /// Number of tracks.
+    pub track_count: usize,
+    /// Number of points in all track segments.
+    pub total_track_points: usize,
+    /// Number of points in all routes.
+    pub total_route_points: usize,
+    /// Whether any waypoint, route point, or track point has an elevation.
+    pub has_elevation: bool,
+    /// Whether any waypoint, route point, or track point has a timestamp.
+    pub has_timestamps: bool,
+    /// Area spanned by all waypoints, route points, and track points.
+    ///
+    /// This is `None` if there are no points.
+    pub bounding_box: Option<BoundingBox>,
+}
+
+/// Inspect the contents of `gpx` data without converting it.
+///
+/// This allows deciding whether to convert GPX data, e.g., by rejecting
+/// files without timestamps. No memory is allocated.
+///
+/// # Example
+/// ```
+/// # use gpx_kml_convert::gpx_statistics;
+/// #
+/// let source = r#"<?xml version="1.0" encoding="UTF-8"?>
+/// <gpx xmlns="http://www.topografix.com/GPX/1/1" version="1.1">
+///     <wpt lat="48.858222" lon="2.2945"><ele>30</ele></wpt>
+///     <rte><rtept lat="48.858222" lon="2.2945"/><rtept lat="48.8606" lon="2.3376"/></rte>
+/// </gpx>
+/// "#;
+/// let gpx = gpx::read(source.as_bytes()).expect("GPX is invalid");
+///
+/// let statistics = gpx_statistics(&gpx);
+/// assert_eq!(statistics.waypoint_count, 1);
+/// assert_eq!(statistics.total_route_points, 2);
+/// assert!(statistics.has_elevation);
+/// assert!(!statistics.has_timestamps);
+/// ```
+pub fn gpx_statistics(gpx: &Gpx) -> GpxStatistics {
+    let route_points = gpx.routes.iter().flat_map(|r| &r.points);
+    let track_points = gpx
+        .tracks
+        .iter()
+        .flat_map(|t| &t.segments)
+        .flat_map(|s| &s.points);
+
+    let mut statistics = GpxStatistics {
+        waypoint_count: gpx.waypoints.len(),
+        route_count: gpx.routes.len(),
+        track_count: gpx.tracks.len(),
+        total_track_points: track_points.clone().count(),
+        total_route_points: route_points.clone().count(),
+        ..Default::default()
+    };
+    for waypoint in gpx.waypoints.iter().chain(route_points).chain(track_points) {
+        statistics.has_elevation |= waypoint.elevation.is_some();
+        statistics.has_timestamps |= waypoint.time.is_some();
+        let point = waypoint.point();
+        statistics
+            .bounding_box
+            .get_or_insert_with(BoundingBox::default)
+            .expand(point.y(), point.x());
+    }
+    statistics
+}
+
+/// Read a GPX file and write a KML file with a single placemark summarizing
+/// it.
+///
+/// The placemark is located at the center of the bounding box and describes
+/// the [`GpxStatistics`] of the GPX data. It is named like the GPX data. This
+/// is useful for showing what a GPX file contains without loading all of its
+/// geometry. GPX data without any points results in a placemark without
+/// geometry.
+///
+/// # Example
+/// ```
+/// # use gpx_kml_convert::gpx_summary_to_kml;
+/// #
+/// let source = r#"<?xml version="1.0" encoding="UTF-8"?>
+/// <gpx xmlns="http://www.topografix.com/GPX/1/1" version="1.1">
+///     <metadata><name>Paris</name></metadata>
+///     <rte><rtept lat="48.858222" lon="2.2945"/><rtept lat="48.8606" lon="2.3376"/></rte>
+/// </gpx>
+/// "#;
+/// let mut sink = vec![];
+///
+/// gpx_summary_to_kml(source.as_bytes(), &mut sink).expect("conversion failed");
+///
+/// let kml = String::from_utf8(sink).expect("KML data is not valid UTF-8");
+/// assert_eq!(kml.matches("<Placemark>").count(), 1);
+/// assert!(kml.contains("Routes: 1 (2 points)"));
+/// ```
+#[must_use = "the conversion might have failed"]
+pub fn gpx_summary_to_kml(source: impl Read, sink: impl std::io::Write) -> Result<(), Error> {
+    let gpx = gpx::read(source)?;
+    let statistics = gpx_statistics(&gpx);
+
+    let mut description = String::new();
+    writeln!(description, "Waypoints: {}", statistics.waypoint_count).unwrap();
+    writeln!(
+        description,
+        "Routes: {} ({} points)",
+        statistics.route_count, statistics.total_route_points
+    )
+    .unwrap();
+    writeln!(
+        description,
+        "Tracks: {} ({} points)",
+        statistics.track_count, statistics.total_track_points
+    )
+    .unwrap();
+    let yes_no = |b| if b { "yes" } else { "no" };
+    writeln!(
+        description,
+        "Elevation: {}",
+        yes_no(statistics.has_elevation)
+    )
+    .unwrap();
+    writeln!(
+        description,
+        "Timestamps: {}",
+        yes_no(statistics.has_timestamps)
+    )
+    .unwrap();
+    if let Some(bbox) = statistics.bounding_box {
+        writeln!(description, "Bounding box: {bbox}").unwrap();
+    }
+
+    let geometry = statistics.bounding_box.map(|bbox| {
+        Geometry::Point(Point {
+            coord: Coord {
+                x: (bbox.min_lon + bbox.max_lon) / 2.0,
+                y: (bbox.min_lat + bbox.max_lat) / 2.0,
+                z: None,
+            },
+            ..Default::default()
+        })
+    });
+    let placemark = Kml::Placemark(Placemark {
+        name: gpx.metadata.and_then(|m| m.name),
+        description: Some(description),
+        geometry,
+        ..Default::default()
+    });
+    let document = Kml::Document {
+        attrs: Default::default(),
+        elements: vec![simple_kelem("open", DEFAULT_OPEN), placemark],
+    };
+    write_kml::<f64>(
+        vec![document],
+        None,
+        &[],
+        sink,
+        &ConversionOptions::default(),
+    )
+}
+
+/// Collection of GPX features which can be turned into [`Gpx`] data.
+///
+/// This implements [`Extend`] and [`FromIterator`] for waypoints, routes, and
+/// tracks, which is handy for iterator-heavy code.
+///
+/// # Example
+/// ```
+/// # use gpx_kml_convert::{convert_gpx, GpxAccumulator};
+/// #
+/// let waypoints = [(2.2945, 48.858222), (12.492231, 41.89021)]
+///     .into_iter()
+///     .map(|(lon, lat)| gpx::Waypoint::new((lon, lat).into()));
+/// let accumulator: GpxAccumulator = waypoints.collect();
+/// let mut sink = vec![];
+///
+/// let _ = convert_gpx(accumulator.into_gpx(), &mut sink, &Default::default())
+///     .expect("conversion failed");
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GpxAccumulator {
+    waypoints: Vec<Waypoint>,
+    routes: Vec<Route>,
+    tracks: Vec<Track>,
+}
+
+impl GpxAccumulator {
+    /// Create an empty accumulator.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Turn the accumulated features into GPX 1.1 data without metadata.
+    pub fn into_gpx(self) -> Gpx {
+        Gpx {
+            version: GpxVersion::Gpx11,
+            waypoints: self.waypoints,
+            routes: self.routes,
+            tracks: self.tracks,
+            ..Default::default()
+        }
+    }
+}
+
+impl Extend<Waypoint> for GpxAccumulator {
+    fn extend<I: IntoIterator<Item = Waypoint>>(&mut self, iter: I) {
+        self.waypoints.extend(iter);
+    }
+}
+
+impl Extend<Route> for GpxAccumulator {
+    fn extend<I: IntoIterator<Item = Route>>(&mut self, iter: I) {
+        self.routes.extend(iter);
+    }
+}
+
+impl Extend<Track> for GpxAccumulator {
+    fn extend<I: IntoIterator<Item = Track>>(&mut self, iter: I) {
+        self.tracks.extend(iter);
+    }
+}
+
+impl<T> FromIterator<T> for GpxAccumulator
+where
+    GpxAccumulator: Extend<T>,
+{
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut accumulator = Self::new();
+        accumulator.extend(iter);
+        accumulator
+    }
 }
 
 /// Read a GPX file and write a KML file.
@@ -85,56 +1106,642 @@ pub enum Error {
 /// assert!(kml.contains("48.858222"));
 /// assert!(kml.contains("Eiffel Tower"));
 /// ```
-pub fn convert(source: impl Read, mut sink: impl io::Write) -> Result<(), Error> {
+#[must_use = "the conversion might have failed"]
+pub fn convert(source: impl Read, sink: impl std::io::Write) -> Result<(), Error> {
     let gpx = gpx::read(source)?;
+    convert_gpx(gpx, sink, &ConversionOptions::default()).map(drop)
+}
+
+/// Read a GPX file and write a KML file using the given `options`.
+///
+/// This works like [`convert`] but allows customizing the output. The returned
+/// [`ConversionReport`] contains information about the conversion.
+///
+/// The durations in the report are zero on `wasm32-unknown-unknown`, which
+/// has no clock for measuring them.
+///
+/// # Example
+/// ```
+/// # use gpx_kml_convert::{convert_with_options, ConversionOptions};
+/// #
+/// let source = r#"<?xml version="1.0" encoding="UTF-8"?>
+/// <gpx xmlns="http://www.topografix.com/GPX/1/1" version="1.1">
+///     <rte><rtept lat="48.858222" lon="2.2945"/><rtept lat="48.8606" lon="2.3376"/></rte>
+/// </gpx>
+/// "#;
+/// let options = ConversionOptions {
+///     tessellate: false,
+///     ..Default::default()
+/// };
+/// let mut sink = vec![];
+///
+/// let report =
+///     convert_with_options(source.as_bytes(), &mut sink, &options).expect("conversion failed");
+/// println!("conversion took {:?}", report.convert_duration);
+///
+/// let kml = String::from_utf8(sink).expect("KML data is not valid UTF-8");
+/// assert!(kml.contains("<tessellate>0</tessellate>"));
+/// ```
+#[must_use = "the conversion might have failed"]
+pub fn convert_with_options(
+    source: impl Read,
+    sink: impl std::io::Write,
+    options: &ConversionOptions,
+) -> Result<ConversionReport, Error> {
+    read_and_convert(source, vec![], sink, options)
+}
+
+/// Read a GPX file and write a KML file with a unique ID.
+///
+/// This works like [`convert_with_options`] but generates a random UUID for
+/// the conversion. The ID is returned, set in [`ConversionReport::id`], and
+/// added as comment like `<!-- Conversion-ID: 67e55044-... -->` after the XML
+/// declaration. It can be used as correlation ID in logs.
+///
+/// Note that the reader of the `kml` crate fails on XML comments.
+#[cfg(feature = "uuid")]
+#[must_use = "the conversion might have failed"]
+pub fn convert_with_id(
+    source: impl Read,
+    sink: impl std::io::Write,
+    options: &ConversionOptions,
+) -> Result<(ConversionReport, uuid::Uuid), Error> {
+    let id = uuid::Uuid::new_v4();
+    let mut report = read_and_convert(source, vec![format!("Conversion-ID: {id}")], sink, options)?;
+    report.id = Some(id);
+    Ok((report, id))
+}
+
+/// Implementation of [`convert_with_options`] adding the `comments` to the
+/// KML output.
+fn read_and_convert(
+    source: impl Read,
+    mut comments: Vec<String>,
+    sink: impl std::io::Write,
+    options: &ConversionOptions,
+) -> Result<ConversionReport, Error> {
+    let start = Stopwatch::start();
+    let (gpx, checksum) = read_gpx(source, options)?;
+    let parse_duration = start.elapsed();
+    comments.extend(checksum.map(checksum_comment));
+
+    let start = Stopwatch::start();
+    let mut report = convert_parsed(gpx, &comments, sink, options)?;
+    report.parse_duration = parse_duration;
+    report.convert_duration = start.elapsed();
+    Ok(report)
+}
+
+/// Read a GPX file and write a KML file while logging to `logger`.
+///
+/// This works like [`convert_with_options`] but additionally logs structured
+/// records about the conversion, e.g., the number of converted features and
+/// the elapsed time.
+#[cfg(feature = "slog")]
+#[must_use = "the conversion might have failed"]
+pub fn convert_with_logger(
+    source: impl Read,
+    sink: impl std::io::Write,
+    options: &ConversionOptions,
+    logger: &slog::Logger,
+) -> Result<ConversionReport, Error> {
+    let start = Stopwatch::start();
+    let result = read_and_convert(source, vec![], sink, options);
+    let elapsed_ms = start.elapsed().as_millis() as u64;
+    match result {
+        Ok(ref report) => slog::info!(logger, "conversion finished";
+            "waypoints_count" => report.waypoint_count,
+            "routes_count" => report.route_count,
+            "tracks_count" => report.track_count,
+            "warnings_count" => report.warnings.len(),
+            "parse_ms" => report.parse_duration.as_millis() as u64,
+            "elapsed_ms" => elapsed_ms,
+        ),
+        Err(ref err) => {
+            slog::error!(logger, "conversion failed"; "error" => %err, "elapsed_ms" => elapsed_ms)
+        }
+    }
+    result
+}
+
+/// Convert already parsed `gpx` data and write a KML file to `sink`.
+///
+/// This is useful if the GPX data was constructed programmatically or needs to
+/// be inspected before the conversion. See [`convert_with_options`] for
+/// details.
+#[must_use = "the conversion might have failed"]
+pub fn convert_gpx(
+    gpx: Gpx,
+    sink: impl std::io::Write,
+    options: &ConversionOptions,
+) -> Result<ConversionReport, Error> {
+    convert_parsed(gpx, &[], sink, options)
+}
+
+/// Read the GPX data from `source`.
+///
+/// The CRC32 `checksum` of the input is returned if requested by the `options`.
+fn read_gpx(source: impl Read, options: &ConversionOptions) -> Result<(Gpx, Option<u32>), Error> {
+    if !options.add_checksum {
+        return Ok((parse_gpx(source, options)?, None));
+    }
+
+    let mut reader = ChecksumReader::new(source);
+    let gpx = parse_gpx(&mut reader, options)?;
+    Ok((gpx, Some(reader.finish()?)))
+}
+
+/// Parse the GPX data from `source` in the
+/// [`input_format`](ConversionOptions::input_format) of the `options`.
+///
+/// This allows inspecting the GPX data before converting it with
+/// [`convert_gpx`], e.g., for comparing it with [`diff_gpx_kml`] afterwards.
+///
+/// # Errors
+/// Fails like [`convert_with_options`] if the input cannot be parsed.
+///
+/// # Example
+/// ```
+/// # use gpx_kml_convert::{parse_gpx, ConversionOptions, InputFormat};
+/// #
+/// let source = r#"<gpx version="1.1.0"><wpt lat="48.858222" lon="2.2945"/></gpx>"#;
+/// let options = ConversionOptions {
+///     input_format: InputFormat::Gpx11,
+///     ..Default::default()
+/// };
+///
+/// let gpx = parse_gpx(source.as_bytes(), &options).expect("GPX is invalid");
+/// assert_eq!(gpx.waypoints.len(), 1);
+/// ```
+pub fn parse_gpx(mut source: impl Read, options: &ConversionOptions) -> Result<Gpx, Error> {
+    let version = match options.input_format {
+        InputFormat::Auto => return Ok(gpx::read(source)?),
+        InputFormat::Gpx10 => "1.0",
+        InputFormat::Gpx11 => "1.1",
+    };
+
+    let mut input = vec![];
+    source.read_to_end(&mut input)?;
+    let mut patched = vec![];
+    xml::force_gpx_version(&input, version, &mut patched)
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+    Ok(gpx::read(patched.as_slice())?)
+}
+
+/// Create the comment containing the CRC32 `checksum` of a GPX input.
+fn checksum_comment(checksum: u32) -> String {
+    format!("GPX-CRC32: {checksum:08X}")
+}
+
+/// Implementation of [`convert_gpx`] adding the `comments` to the KML output.
+fn convert_parsed(
+    gpx: Gpx,
+    comments: &[String],
+    sink: impl std::io::Write,
+    options: &ConversionOptions,
+) -> Result<ConversionReport, Error> {
+    match options.coordinate_type {
+        CoordinateType::F64 => convert_gpx_as::<f64>(gpx, comments, sink, options),
+        CoordinateType::F32 => convert_gpx_as::<f32>(gpx, comments, sink, options),
+    }
+}
+
+/// Implementation of [`convert_parsed`] with coordinates of type `T`.
+fn convert_gpx_as<T: CoordValue>(
+    gpx: Gpx,
+    comments: &[String],
+    sink: impl std::io::Write,
+    options: &ConversionOptions,
+) -> Result<ConversionReport, Error> {
+    let feed = options
+        .atom_feed_mode
+        .then(|| AtomFeed::new(gpx.metadata.as_ref(), options.name_override.as_deref()));
+    let (document, report) = convert_document::<T>(gpx, options)?;
+    write_kml(vec![document], feed, comments, sink, options)?;
+    Ok(report)
+}
+
+/// Read a GPX file and return the KML file as indented string.
+///
+/// This is meant for inspecting the conversion output while debugging. Each
+/// element and each coordinate tuple starts on a new line. Otherwise, the
+/// output is the same as the one from [`convert`].
+///
+/// # Example
+/// ```
+/// # use gpx_kml_convert::convert_to_pretty_kml;
+/// #
+/// let source = r#"<?xml version="1.0" encoding="UTF-8"?>
+/// <gpx xmlns="http://www.topografix.com/GPX/1/1" version="1.1">
+///     <wpt lat="48.858222" lon="2.2945"><name>Eiffel Tower</name></wpt>
+/// </gpx>
+/// "#;
+///
+/// let kml = convert_to_pretty_kml(source.as_bytes()).expect("conversion failed");
+/// println!("{kml}");
+/// assert!(kml.contains("\n    <Placemark>\n      <name>Eiffel Tower</name>\n"));
+/// ```
+#[must_use = "the conversion might have failed"]
+pub fn convert_to_pretty_kml(source: impl Read) -> Result<String, Error> {
+    let gpx = gpx::read(source)?;
+    let mut kml = vec![];
+    let _ = convert_gpx(gpx, &mut kml, &ConversionOptions::default())?;
+    let mut pretty = vec![];
+    xml::indent(&kml, &mut pretty).map_err(|err| Error::Xml(err.to_string()))?;
+    Ok(String::from_utf8(pretty).expect("KML data is not valid UTF-8"))
+}
+
+/// Read several GPX files and write them as separate documents of one KML
+/// file.
+///
+/// Each source becomes a KML _Document_ in the order of `sources`. This is an
+/// alternative to merging the sources into one document. A [`ConversionReport`]
+/// is returned for each source. Their
+/// [`convert_duration`](ConversionReport::convert_duration) excludes writing
+/// the combined KML file.
+///
+/// The conversion fails as a whole if any source cannot be read. In
+/// [`atom_feed_mode`](ConversionOptions::atom_feed_mode), the feed is derived
+/// from the metadata of the first source.
+#[must_use = "the conversion might have failed"]
+pub fn convert_to_multi_document(
+    sources: impl IntoIterator<Item = impl Read>,
+    sink: impl std::io::Write,
+    options: &ConversionOptions,
+) -> Result<Vec<ConversionReport>, Error> {
+    match options.coordinate_type {
+        CoordinateType::F64 => convert_to_multi_document_as::<f64>(sources, sink, options),
+        CoordinateType::F32 => convert_to_multi_document_as::<f32>(sources, sink, options),
+    }
+}
+
+/// Implementation of [`convert_to_multi_document`] with coordinates of type
+/// `T`.
+fn convert_to_multi_document_as<T: CoordValue>(
+    sources: impl IntoIterator<Item = impl Read>,
+    sink: impl std::io::Write,
+    options: &ConversionOptions,
+) -> Result<Vec<ConversionReport>, Error> {
+    let mut parsed = vec![];
+    let mut comments = vec![];
+    for source in sources {
+        let start = Stopwatch::start();
+        let (gpx, checksum) = read_gpx(source, options)?;
+        parsed.push((gpx, start.elapsed()));
+        comments.extend(checksum.map(checksum_comment));
+    }
+    let feed = parsed
+        .first()
+        .filter(|_| options.atom_feed_mode)
+        .map(|(gpx, _)| AtomFeed::new(gpx.metadata.as_ref(), options.name_override.as_deref()));
+    if options.merge_tracks_by_name {
+        merge_tracks_across_documents(&mut parsed);
+    }
+
+    let mut documents = vec![];
+    let mut reports = vec![];
+    for (gpx, parse_duration) in parsed {
+        let start = Stopwatch::start();
+        let (document, mut report) = convert_document::<T>(gpx, options)?;
+        report.parse_duration = parse_duration;
+        report.convert_duration = start.elapsed();
+        documents.push(document);
+        reports.push(report);
+    }
+
+    write_kml(documents, feed, &comments, sink, options)?;
+    Ok(reports)
+}
+
+/// Convert `gpx` data to a KML _Document_.
+fn convert_document<T: CoordValue>(
+    mut gpx: Gpx,
+    options: &ConversionOptions,
+) -> Result<(Kml<T>, ConversionReport), Error> {
+    if options.merge_tracks_by_name {
+        gpx.tracks = merge_tracks_by_name(gpx.tracks);
+    }
+    if let Some(radius_m) = options.privacy_radius_m {
+        // Trim before anything else so that the report does not reveal the
+        // hidden points either.
+        for track in &mut gpx.tracks {
+            track.segments = mem::take(&mut track.segments)
+                .into_iter()
+                .map(|s| trim_privacy_zone(s.points, radius_m))
+                .filter(|points| !points.is_empty())
+                .map(|points| TrackSegment { points })
+                .collect();
+        }
+    }
+    let mut warnings = vec![];
+    gpx.tracks
+        .retain(|track| keep_track(track, options, &mut warnings));
+    gpx.routes
+        .retain(|route| keep_route(route, options, &mut warnings));
+    if options.require_content
+        && gpx.waypoints.is_empty()
+        && gpx.routes.is_empty()
+        && gpx.tracks.is_empty()
+    {
+        return Err(Error::EmptyInput);
+    }
+
+    debug!(
+        "converting {} waypoints, {} routes, and {} tracks",
+        gpx.waypoints.len(),
+        gpx.routes.len(),
+        gpx.tracks.len()
+    );
+
+    let mut report = ConversionReport {
+        bounding_box: bounding_box(&gpx, options),
+        route_count: gpx.routes.len(),
+        track_count: gpx.tracks.len(),
+        warnings,
+        ..Default::default()
+    };
 
     let mut elements = vec![simple_kelem("open", DEFAULT_OPEN)];
-    push_metadata(gpx.metadata.unwrap_or_default(), gpx.creator, &mut elements);
+    push_metadata(
+        gpx.metadata.unwrap_or_default(),
+        gpx.creator,
+        options,
+        &mut elements,
+    );
 
+    let total = gpx.waypoints.len() + gpx.routes.len() + gpx.tracks.len();
+    let mut progress = Progress::start(&options.progress, total);
     for waypoint in gpx.waypoints {
-        elements.push(convert_waypoint(waypoint));
+        if in_bbox(&waypoint, options) {
+            elements.push(convert_waypoint(waypoint, options));
+            report.waypoint_count += 1;
+        }
+        progress.advance();
     }
 
     for route in gpx.routes {
-        elements.push(convert_route(route));
+        elements.push(convert_route(route, options));
+        progress.advance();
     }
 
-    for track in gpx.tracks {
-        elements.push(convert_track(track));
+    match options.split_on_elevation_change {
+        Some(threshold_m) => {
+            let mut ascents = vec![];
+            let mut descents = vec![];
+            let mut levels = vec![];
+            for track in gpx.tracks {
+                let (ascent, descent, level) = split_track_by_elevation(track, threshold_m);
+                ascents.extend(ascent.map(|t| convert_track(t, options)));
+                descents.extend(descent.map(|t| convert_track(t, options)));
+                levels.extend(level.map(|t| convert_track(t, options)));
+                progress.advance();
+            }
+            for (name, folder) in [("Ascents", ascents), ("Descents", descents)] {
+                if !folder.is_empty() {
+                    let mut folder_elements = vec![simple_kelem("name", name)];
+                    folder_elements.extend(folder);
+                    elements.push(Kml::Folder {
+                        attrs: Default::default(),
+                        elements: folder_elements,
+                    });
+                }
+            }
+            elements.extend(levels);
+        }
+        None => {
+            for track in gpx.tracks {
+                elements.push(convert_track(track, options));
+                progress.advance();
+            }
+        }
     }
+    progress.finish();
 
     let document = Kml::Document {
         elements,
         attrs: Default::default(),
     };
-    let namespaces = NAMESPACES
+    Ok((document, report))
+}
+
+/// Whether the `track` passes the track filters of the `options`.
+///
+/// A warning is added to `warnings` for dropped tracks.
+fn keep_track(track: &Track, options: &ConversionOptions, warnings: &mut Vec<Warning>) -> bool {
+    let name = track.name.as_deref().unwrap_or_default();
+    if let Some(max_days) = options.max_track_age_days {
+        // The system time is only queried here as it is unavailable on some
+        // platforms, where the age of the track is unknown.
+        let age_days = clock::system_time().and_then(|now| track_age_days(track, now));
+        if let Some(age_days) = age_days.filter(|&a| a > f64::from(max_days)) {
+            push_warning(
+                Warning::TrackDroppedTooOld {
+                    name: name.to_string(),
+                    age_days,
+                },
+                warnings,
+            );
+            return false;
+        }
+    }
+    if let Some(min_m) = options.min_track_length_m {
+        let length_m: f64 = track
+            .segments
+            .iter()
+            .map(|s| total_length_m(&s.points))
+            .sum();
+        if length_m < min_m {
+            push_warning(
+                Warning::TrackDroppedTooShort {
+                    name: name.to_string(),
+                    length_m,
+                },
+                warnings,
+            );
+            return false;
+        }
+    }
+    if let Some(min_duration) = options.min_track_duration {
+        let segments = track.segments.iter().map(|s| s.points.as_slice());
+        if let Some(duration) = TrackStatistics::new(segments)
+            .duration
+            .filter(|&d| d < min_duration)
+        {
+            push_warning(
+                Warning::TrackDroppedTooBrief {
+                    name: name.to_string(),
+                    duration_s: duration.as_secs_f64(),
+                },
+                warnings,
+            );
+            return false;
+        }
+    }
+    true
+}
+
+/// Whether the `route` passes the route filters of the `options`.
+///
+/// A warning is added to `warnings` for dropped routes.
+fn keep_route(route: &Route, options: &ConversionOptions, warnings: &mut Vec<Warning>) -> bool {
+    if let Some(min_m) = options.min_route_length_m {
+        let length_m = total_length_m(&route.points);
+        if length_m < min_m {
+            push_warning(
+                Warning::RouteDroppedTooShort {
+                    name: route.name.clone().unwrap_or_default(),
+                    length_m,
+                },
+                warnings,
+            );
+            return false;
+        }
+    }
+    true
+}
+
+/// Log the `warning` and add it to the `warnings`.
+fn push_warning(warning: Warning, warnings: &mut Vec<Warning>) {
+    warn!("{warning}");
+    warnings.push(warning);
+}
+
+/// Age in days of the latest timestamp of the `track` at time `now`.
+///
+/// Returns `None` if the track has no timestamps.
+fn track_age_days(track: &Track, now: SystemTime) -> Option<f64> {
+    let latest = track
+        .segments
+        .iter()
+        .flat_map(|s| &s.points)
+        .filter_map(|p| p.time)
+        .map(|t| time::OffsetDateTime::from(t).unix_timestamp())
+        .max()?;
+    let now = match now.duration_since(UNIX_EPOCH) {
+        Ok(since) => since.as_secs_f64(),
+        Err(err) => -err.duration().as_secs_f64(),
+    };
+    Some((now - latest as f64) / 86_400.0)
+}
+
+/// Merge the segments of `tracks` with identical names into the first of them.
+fn merge_tracks_by_name(tracks: Vec<Track>) -> Vec<Track> {
+    let mut merged: Vec<Track> = vec![];
+    let mut indices: HashMap<String, usize> = HashMap::new();
+    for track in tracks {
+        let Some(name) = track.name.clone() else {
+            merged.push(track);
+            continue;
+        };
+        match indices.get(&name) {
+            Some(&i) => merged[i].segments.extend(track.segments),
+            None => {
+                indices.insert(name, merged.len());
+                merged.push(track);
+            }
+        }
+    }
+    merged
+}
+
+/// Merge the tracks of all `documents` with identical names into the first of
+/// them.
+///
+/// Merged tracks are moved to the document of their first occurrence.
+fn merge_tracks_across_documents<D>(documents: &mut [(Gpx, D)]) {
+    let mut indices: HashMap<String, (usize, usize)> = HashMap::new();
+    for i in 0..documents.len() {
+        for track in merge_tracks_by_name(mem::take(&mut documents[i].0.tracks)) {
+            let Some(name) = track.name.clone() else {
+                documents[i].0.tracks.push(track);
+                continue;
+            };
+            match indices.get(&name) {
+                Some(&(document, j)) => documents[document].0.tracks[j]
+                    .segments
+                    .extend(track.segments),
+                None => {
+                    indices.insert(name, (i, documents[i].0.tracks.len()));
+                    documents[i].0.tracks.push(track);
+                }
+            }
+        }
+    }
+}
+
+/// Write the KML `documents` with an XML header to `sink`.
+///
+/// If a `feed` is given, the KML data is wrapped in it. The `comments`, e.g.,
+/// the checksums of the GPX inputs, are added after the XML header.
+fn write_kml<T: CoordValue>(
+    documents: Vec<Kml<T>>,
+    feed: Option<AtomFeed>,
+    comments: &[String],
+    mut sink: impl std::io::Write,
+    options: &ConversionOptions,
+) -> Result<(), Error> {
+    let mut namespaces: HashMap<_, _> = NAMESPACES
         .iter()
+        .filter(|(k, _)| options.include_atom_elements || *k != ATOM_NAMESPACE)
         .map(|(k, v)| (k.to_string(), v.to_string()))
         .collect();
-    let kml = Kml::<CoordValue>::KmlDocument(KmlDocument {
+    if options.osmand_compatible {
+        namespaces.insert(OSMAND_NAMESPACE.to_string(), OSMAND_URI.to_string());
+    }
+    let kml = Kml::KmlDocument(KmlDocument {
         version: KmlVersion::V22,
         attrs: namespaces,
-        elements: vec![document],
+        elements: documents,
     });
 
-    writeln!(&mut sink, "{XML_HEAD}").unwrap();
-    let mut writer = KmlWriter::from_writer(&mut sink);
-    writer.write(&kml)?;
-    writeln!(&mut sink).unwrap();
+    writeln!(&mut sink, "{XML_HEAD}")?;
+    for comment in comments {
+        writeln!(&mut sink, "<!-- {comment} -->")?;
+    }
+    if options.description_escape == DescriptionEscape::HtmlCdata
+        || options.description_format != DescriptionFormat::PlainText
+        || options.add_osm_link
+        || options.add_directions_link
+    {
+        let mut buffer = vec![];
+        write_kml_content(&kml, feed, &mut buffer)?;
+        xml::cdata_descriptions(&buffer, &mut sink).map_err(|err| Error::Xml(err.to_string()))?;
+    } else {
+        write_kml_content(&kml, feed, &mut sink)?;
+    }
+    writeln!(&mut sink)?;
+    debug!("KML document written");
 
     Ok(())
 }
 
+/// Write `kml`, optionally wrapped in a `feed`, to `sink`.
+fn write_kml_content<T: CoordValue>(
+    kml: &Kml<T>,
+    feed: Option<AtomFeed>,
+    mut sink: impl std::io::Write,
+) -> Result<(), kml::Error> {
+    match feed {
+        Some(feed) => feed.write(kml, sink),
+        None => KmlWriter::from_writer(&mut sink).write(kml),
+    }
+}
+
 /// Convert the GPX `metadata` and `creator` to KML.
 ///
 /// The converted data is pushed to `elements`.
-fn push_metadata(metadata: Metadata, creator: Option<String>, elements: &mut Vec<Kml<CoordValue>>) {
-    if let Some(name) = metadata.name {
+fn push_metadata<T: CoordValue>(
+    metadata: Metadata,
+    creator: Option<String>,
+    options: &ConversionOptions,
+    elements: &mut Vec<Kml<T>>,
+) {
+    if let Some(name) = options.name_override.clone().or(metadata.name) {
         elements.push(simple_kelem("name", name));
     }
 
     let mut children = vec![];
-    if let Some(author) = metadata.author {
+    if let Some(author) = metadata.author.filter(|_| options.include_atom_elements) {
         let mut name = author.name.unwrap_or_default();
         let mail = author.email.unwrap_or_default();
         if !name.is_empty() && !mail.is_empty() {
@@ -146,6 +1753,9 @@ fn push_metadata(metadata: Metadata, creator: Option<String>, elements: &mut Vec
         if !name.is_empty() {
             children.push(simple_element("atom:name", name));
         }
+        if !mail.is_empty() {
+            children.push(simple_element("atom:email", mail));
+        }
 
         if let Some(link) = author.link {
             children.push(atom_link(link.href));
@@ -159,8 +1769,10 @@ fn push_metadata(metadata: Metadata, creator: Option<String>, elements: &mut Vec
         }));
     }
 
-    for link in metadata.links {
-        elements.push(Kml::Element(atom_link(link.href)));
+    if options.include_atom_elements {
+        for link in metadata.links {
+            elements.push(Kml::Element(atom_link(link.href)));
+        }
     }
 
     let mut description = metadata
@@ -170,10 +1782,10 @@ fn push_metadata(metadata: Metadata, creator: Option<String>, elements: &mut Vec
             d
         })
         .unwrap_or_default();
-    let time = metadata.time.and_then(|t| t.format().ok());
+    let time = metadata.time.and_then(format_time);
     if time.is_some() || creator.is_some() {
         description.push_str("Created");
-        if let Some(time) = time {
+        if let Some(ref time) = time {
             write!(description, " {}", time).unwrap();
         }
         if let Some(ref creator) = creator {
@@ -201,79 +1813,167 @@ fn push_metadata(metadata: Metadata, creator: Option<String>, elements: &mut Vec
         description.push('\n');
     }
     if !description.is_empty() {
-        elements.push(simple_kelem("description", description));
+        elements.push(simple_kelem(
+            "description",
+            escape_description(description, options),
+        ));
+    }
+
+    if let Some(time) = time.filter(|_| options.metadata_time_as_timestamp) {
+        elements.push(Kml::Element(Element {
+            name: "TimeStamp".to_string(),
+            children: vec![simple_element("when", time)],
+            ..Default::default()
+        }));
     }
 }
 
 /// Convert a GPX `waypoint`.
 ///
 /// This marks a single point. It is converted to a KML _Point_.
-fn convert_waypoint(waypoint: Waypoint) -> Kml<CoordValue> {
+fn convert_waypoint<T: CoordValue>(waypoint: Waypoint, options: &ConversionOptions) -> Kml<T> {
+    let coord = convert_coord(&waypoint, options);
     let point = waypoint.point();
+    let position = Some((point.y(), point.x()));
     let geometry = Geometry::Point(Point {
-        coord: Coord {
-            x: point.x(),
-            y: point.y(),
-            z: waypoint.elevation,
-        },
-        altitude_mode: if waypoint.elevation.is_some() {
-            AltitudeMode::Absolute
-        } else {
-            Default::default()
-        },
+        altitude_mode: altitude_mode(coord.z.is_some(), options),
+        coord,
         ..Default::default()
     });
 
-    create_placemark(PlacemarkArgs {
-        name: waypoint.name,
-        links: waypoint.links,
-        description: waypoint.description,
-        comment: waypoint.comment,
-        time: waypoint.time.and_then(|t| t.format().ok()),
-        source: waypoint.source,
-        typ: waypoint.type_,
-        geometry,
-    })
+    create_placemark(
+        PlacemarkArgs {
+            name: waypoint.name,
+            number: None,
+            position,
+            elevation: waypoint.elevation,
+            visible: options.waypoints_visible,
+            summary: None,
+            map_link: options.add_osm_link.then(|| {
+                let href = format!(
+                    "https://www.openstreetmap.org/?mlat={}&mlon={}&zoom={}",
+                    point.y(),
+                    point.x(),
+                    options.osm_zoom
+                );
+                html::link(&href, "View on OpenStreetMap")
+            }),
+            is_loop: false,
+            extensions: if options.osmand_compatible {
+                osmand::waypoint_elements(waypoint.symbol.as_deref())
+            } else {
+                vec![]
+            },
+            links: waypoint.links,
+            description: waypoint.description,
+            comment: waypoint.comment,
+            time: waypoint.time.and_then(format_time),
+            source: waypoint.source,
+            typ: waypoint.type_,
+            line_color: None,
+            statistics: None,
+            geometry,
+        },
+        options,
+    )
 }
 
 /// Convert a GPX `route`.
 ///
 /// This is a continuous tour of GPX waypoints. It is converted to a KML
 /// _LineString_.
-fn convert_route(route: Route) -> Kml<CoordValue> {
+fn convert_route<T: CoordValue>(route: Route, options: &ConversionOptions) -> Kml<T> {
+    let points: Vec<_> = route
+        .points
+        .into_iter()
+        .filter(|w| in_bbox(w, options))
+        .collect();
+    let statistics = options
+        .include_statistics
+        .then(|| TrackStatistics::new([points.as_slice()]));
+    let summary = options
+        .use_snippet
+        .then(|| line_summary([points.as_slice()], options));
+    let map_link = options
+        .add_directions_link
+        .then(|| directions_link(&points))
+        .flatten();
+    let is_loop = is_loop(points.first(), points.last(), options);
+
     let mut elevation_avail = false;
     let mut coords = vec![];
-    for waypoint in route.points {
-        let point = waypoint.point();
-        coords.push(Coord {
-            x: point.x(),
-            y: point.y(),
-            z: waypoint.elevation,
-        });
-        elevation_avail |= waypoint.elevation.is_some();
+    for waypoint in points {
+        let coord = convert_coord(&waypoint, options);
+        elevation_avail |= coord.z.is_some();
+        coords.push(coord);
     }
 
     let geometry = Geometry::LineString(LineString {
-        tessellate: DEFAULT_TESSELLATE,
-        altitude_mode: if elevation_avail {
-            AltitudeMode::Absolute
-        } else {
-            Default::default()
-        },
+        tessellate: options.tessellate,
+        altitude_mode: altitude_mode(elevation_avail, options),
         coords,
         ..Default::default()
     });
 
-    create_placemark(PlacemarkArgs {
-        name: route.name,
-        links: route.links,
-        description: route.description,
-        comment: route.comment,
-        time: None,
-        source: route.source,
-        typ: route.type_,
-        geometry,
-    })
+    create_placemark(
+        PlacemarkArgs {
+            line_color: activity_color(route.type_.as_deref(), options),
+            name: route.name,
+            number: route
+                .number
+                .filter(|_| options.include_track_number)
+                .map(|n| format!("Route #{n}")),
+            position: None,
+            elevation: None,
+            visible: true,
+            summary,
+            map_link,
+            is_loop,
+            extensions: vec![],
+            links: route.links,
+            description: route.description,
+            comment: route.comment,
+            time: None,
+            source: route.source,
+            typ: route.type_,
+            statistics,
+            geometry,
+        },
+        options,
+    )
+}
+
+/// Create an HTML link to Google Maps directions along the route `points`.
+///
+/// There is no link for less than two points.
+fn directions_link(points: &[Waypoint]) -> Option<String> {
+    let [first, intermediate @ .., last] = points else {
+        return None;
+    };
+    let position = |waypoint: &Waypoint| {
+        let point = waypoint.point();
+        format!("{},{}", point.y(), point.x())
+    };
+
+    let mut href = format!(
+        "https://maps.google.com/maps?saddr={}&daddr={}",
+        position(first),
+        position(last)
+    );
+    if !intermediate.is_empty() {
+        let count = intermediate.len().min(MAX_DIRECTIONS_WAYPOINTS);
+        // Spread the waypoints evenly from the first to the last intermediate
+        // point.
+        let waypoints: Vec<_> = (0..count)
+            .map(|i| match count {
+                1 => 0,
+                _ => i * (intermediate.len() - 1) / (count - 1),
+            })
+            .map(|i| position(&intermediate[i]))
+            .collect();
+        write!(href, "&waypoints={}", waypoints.join("%7C")).unwrap();
+    }
+    Some(html::link(&href, "Directions on Google Maps"))
 }
 
 /// Convert a GPX `track`.
@@ -281,53 +1981,236 @@ fn convert_route(route: Route) -> Kml<CoordValue> {
 /// This is a structure containing multiple continuous segments of GPX
 /// waypoints. It is converted to a KML _MultiGeometry_. Each segment is
 /// converted with [`convert_segment`].
-fn convert_track(track: Track) -> Kml {
-    let geometries = track.segments.into_iter().map(convert_segment).collect();
-
-    create_placemark(PlacemarkArgs {
-        name: track.name,
-        links: track.links,
-        description: track.description,
-        comment: track.comment,
-        time: None,
-        source: track.source,
-        typ: track.type_,
-        geometry: Geometry::MultiGeometry(MultiGeometry {
-            geometries,
-            ..Default::default()
-        }),
-    })
+fn convert_track<T: CoordValue>(track: Track, options: &ConversionOptions) -> Kml<T> {
+    let segments: Vec<Vec<_>> = track
+        .segments
+        .into_iter()
+        .map(|s| {
+            s.points
+                .into_iter()
+                .filter(|w| in_bbox(w, options) && !in_geofence(w, options))
+                .collect()
+        })
+        .collect();
+    let typ = track.type_.or_else(|| {
+        options
+            .infer_activity_from_speed
+            .then(|| TrackStatistics::new(segments.iter().map(Vec::as_slice)))
+            .and_then(|s| s.average_speed_ms())
+            .map(|speed| infer_activity(speed).to_string())
+    });
+    let statistics = options
+        .include_statistics
+        .then(|| TrackStatistics::new(segments.iter().map(Vec::as_slice)));
+    let is_loop = is_loop(
+        segments.iter().flatten().next(),
+        segments.iter().flatten().next_back(),
+        options,
+    );
+    let summary = options
+        .use_snippet
+        .then(|| line_summary(segments.iter().map(Vec::as_slice), options));
+
+    let geometries = segments
+        .into_iter()
+        .flat_map(|s| match options.max_segment_length_km {
+            Some(max_km) => split_by_length(s, max_km * 1000.0),
+            None => vec![s],
+        })
+        .map(|s| convert_segment(s, options))
+        .collect();
+
+    create_placemark(
+        PlacemarkArgs {
+            line_color: activity_color(typ.as_deref(), options),
+            name: track.name,
+            number: track
+                .number
+                .filter(|_| options.include_track_number)
+                .map(|n| format!("Track #{n}")),
+            position: None,
+            elevation: None,
+            visible: options.tracks_visible,
+            summary,
+            map_link: None,
+            is_loop,
+            extensions: vec![],
+            links: track.links,
+            description: track.description,
+            comment: track.comment,
+            time: None,
+            source: track.source,
+            typ,
+            statistics,
+            geometry: Geometry::MultiGeometry(MultiGeometry {
+                geometries,
+                ..Default::default()
+            }),
+        },
+        options,
+    )
+}
+
+/// Whether a line from `first` to `last` is a loop according to the
+/// `options`.
+fn is_loop(first: Option<&Waypoint>, last: Option<&Waypoint>, options: &ConversionOptions) -> bool {
+    match (first, last) {
+        // A single point is no loop.
+        (Some(first), Some(last)) if options.detect_loops && !ptr::eq(first, last) => {
+            haversine_m(first, last) <= options.loop_threshold_m
+        }
+        _ => false,
+    }
+}
+
+/// Split the segments of a `track` into an ascending and a descending track.
+///
+/// See [`split_by_elevation`] for details. Segments without elevation are
+/// collected in a third track. Tracks without segments are omitted.
+fn split_track_by_elevation(
+    mut track: Track,
+    threshold_m: f64,
+) -> (Option<Track>, Option<Track>, Option<Track>) {
+    let mut ascent = vec![];
+    let mut descent = vec![];
+    let mut level = vec![];
+    for segment in mem::take(&mut track.segments) {
+        if segment.points.iter().all(|p| p.elevation.is_none()) {
+            level.push(segment);
+            continue;
+        }
+        for (ascending, points) in split_by_elevation(segment.points, threshold_m) {
+            let segments = if ascending { &mut ascent } else { &mut descent };
+            segments.push(TrackSegment { points });
+        }
+    }
+
+    let with_segments = |segments: Vec<TrackSegment>| {
+        (!segments.is_empty()).then(|| Track {
+            segments,
+            ..track.clone()
+        })
+    };
+    (
+        with_segments(ascent),
+        with_segments(descent),
+        with_segments(level),
+    )
 }
 
-/// Convert a single track `segment` to a KML _LineString_.
-fn convert_segment(segment: TrackSegment) -> Geometry {
+/// Convert the `points` of a single track segment to a KML _LineString_.
+fn convert_segment<T: CoordValue>(
+    points: Vec<Waypoint>,
+    options: &ConversionOptions,
+) -> Geometry<T> {
     let mut elevation_avail = false;
     let mut coords = vec![];
-    for waypoint in segment.points {
-        let point = waypoint.point();
-        coords.push(Coord {
-            x: point.x(),
-            y: point.y(),
-            z: waypoint.elevation,
-        });
-        elevation_avail |= waypoint.elevation.is_some();
+    for waypoint in points {
+        let coord = convert_coord(&waypoint, options);
+        elevation_avail |= coord.z.is_some();
+        coords.push(coord);
     }
 
     Geometry::LineString(LineString {
-        tessellate: DEFAULT_TESSELLATE,
-        altitude_mode: if elevation_avail {
-            AltitudeMode::Absolute
-        } else {
-            Default::default()
-        },
+        tessellate: options.tessellate,
+        altitude_mode: altitude_mode(elevation_avail, options),
         coords,
         ..Default::default()
     })
 }
 
+/// Compute the box around all points of `gpx` which pass the `options`.
+fn bounding_box(gpx: &Gpx, options: &ConversionOptions) -> BoundingBox {
+    let routes = gpx.routes.iter().flat_map(|r| &r.points);
+    let tracks = gpx
+        .tracks
+        .iter()
+        .flat_map(|t| &t.segments)
+        .flat_map(|s| &s.points)
+        .filter(|w| !in_geofence(w, options));
+
+    let mut bbox = BoundingBox::default();
+    for waypoint in gpx.waypoints.iter().chain(routes).chain(tracks) {
+        if in_bbox(waypoint, options) {
+            let point = waypoint.point();
+            bbox.expand(point.y(), point.x());
+        }
+    }
+    bbox
+}
+
+/// Whether the `waypoint` passes the bounding box filter of the `options`.
+fn in_bbox(waypoint: &Waypoint, options: &ConversionOptions) -> bool {
+    let point = waypoint.point();
+    options
+        .bbox_filter
+        .is_none_or(|bbox| bbox.contains(point.y(), point.x()))
+}
+
+/// Whether the `waypoint` lies strictly inside one of the geofence exclusions
+/// of the `options`.
+fn in_geofence(waypoint: &Waypoint, options: &ConversionOptions) -> bool {
+    let point = waypoint.point();
+    let (lat, lon) = (point.y(), point.x());
+    options.geofence_exclusions.iter().any(|bbox| {
+        bbox.min_lat < lat && lat < bbox.max_lat && bbox.min_lon < lon && lon < bbox.max_lon
+    })
+}
+
+/// Convert the position of a GPX `waypoint` to a KML coordinate.
+fn convert_coord<T: CoordValue>(waypoint: &Waypoint, options: &ConversionOptions) -> Coord<T> {
+    let point = waypoint.point();
+    Coord {
+        x: T::from_f64(point.x()),
+        y: T::from_f64(point.y()),
+        z: waypoint
+            .elevation
+            .filter(|&z| !(options.omit_zero_altitude && z == 0.0))
+            .map(T::from_f64),
+    }
+}
+
+/// Select the altitude mode for a geometry.
+///
+/// Unless overridden by the `options`, absolute altitudes are only used if
+/// elevation data is available.
+fn altitude_mode(elevation_avail: bool, options: &ConversionOptions) -> AltitudeMode {
+    options.altitude_mode.unwrap_or(if elevation_avail {
+        AltitudeMode::Absolute
+    } else {
+        Default::default()
+    })
+}
+
+/// Look up the line color for the activity `typ` in the `options`.
+fn activity_color(typ: Option<&str>, options: &ConversionOptions) -> Option<String> {
+    let typ = normalize_activity_type(typ?);
+    options
+        .activity_colors
+        .iter()
+        .find(|(k, _)| normalize_activity_type(k) == typ)
+        .map(|(_, color)| color.clone())
+}
+
 /// Argument for the [`create_placemark`] function.
-struct PlacemarkArgs {
+struct PlacemarkArgs<T: CoordValue> {
     name: Option<String>,
+    /// Label like `Track #3` to start the description with.
+    number: Option<String>,
+    /// Latitude and longitude of a single point.
+    position: Option<(f64, f64)>,
+    /// Elevation of a single point in meters.
+    elevation: Option<f64>,
+    /// Whether the placemark is visible by default.
+    visible: bool,
+    /// Summary of a line for the _Snippet_ if there is no description.
+    summary: Option<String>,
+    /// HTML link to a map service for appending to the description.
+    map_link: Option<String>,
+    /// Whether the line ends near its start.
+    is_loop: bool,
+    /// Additional elements of the placemark, e.g., for OsmAnd.
+    extensions: Vec<Element>,
     links: Vec<Link>,
     description: Option<String>,
     comment: Option<String>,
@@ -335,34 +2218,74 @@ struct PlacemarkArgs {
     source: Option<String>,
     /// _type_ attribute in GPX.
     typ: Option<String>,
-    geometry: Geometry,
+    /// Line color in KML notation.
+    line_color: Option<String>,
+    statistics: Option<TrackStatistics>,
+    geometry: Geometry<T>,
 }
 
 /// Create a KML _Placemark_, which describes displayed geometry.
-fn create_placemark(args: PlacemarkArgs) -> Kml<CoordValue> {
+fn create_placemark<T: CoordValue>(args: PlacemarkArgs<T>, options: &ConversionOptions) -> Kml<T> {
+    let description = match options.description_format {
+        DescriptionFormat::PlainText => plain_description(&args, options),
+        DescriptionFormat::HtmlTable => html::table(&html_rows(&args, true, options)),
+        DescriptionFormat::HtmlCard => {
+            let paragraphs: Vec<_> = [&args.description, &args.comment]
+                .into_iter()
+                .flatten()
+                .map(|text| escape_description(text.clone(), options))
+                .collect();
+            html::card(
+                args.name.as_deref(),
+                &paragraphs,
+                &html_rows(&args, false, options),
+            )
+        }
+    };
+    let description = append_line(description, args.map_link.as_deref());
+    let description = add_affixes(description, options);
+
     let mut children = vec![];
-    for link in args.links {
-        children.push(atom_link(link.href));
+    if !args.visible {
+        children.push(simple_element("visibility", "0"));
     }
-
-    let mut description = args
-        .description
-        .map(|mut d| {
-            d.push('\n');
-            d
-        })
-        .unwrap_or_default();
-    if let Some(comment) = args.comment {
-        writeln!(description, "{}", comment).unwrap();
+    if let Some(snippet) = snippet(&args).filter(|_| options.use_snippet) {
+        children.push(Element {
+            name: "Snippet".to_string(),
+            attrs: HashMap::from([("maxLines".to_string(), SNIPPET_MAX_LINES.to_string())]),
+            content: Some(snippet),
+            ..Default::default()
+        });
     }
-    if let Some(time) = args.time {
-        writeln!(description, "Created {}", time).unwrap();
+    if options.include_atom_elements {
+        for link in args.links {
+            children.push(atom_link(link.href));
+        }
     }
-    if let Some(source) = args.source {
-        writeln!(description, "Source: {}", source).unwrap();
+
+    if args.is_loop {
+        children.push(Element {
+            name: "ExtendedData".to_string(),
+            children: vec![Element {
+                name: "Data".to_string(),
+                attrs: HashMap::from([("name".to_string(), "is_loop".to_string())]),
+                children: vec![simple_element("value", "true")],
+                ..Default::default()
+            }],
+            ..Default::default()
+        });
     }
-    if let Some(typ) = args.typ {
-        writeln!(description, "Type: {}", typ).unwrap();
+    children.extend(args.extensions);
+    if let Some(color) = args.line_color {
+        children.push(Element {
+            name: "Style".to_string(),
+            children: vec![Element {
+                name: "LineStyle".to_string(),
+                children: vec![simple_element("color", color)],
+                ..Default::default()
+            }],
+            ..Default::default()
+        });
     }
 
     Kml::Placemark(Placemark {
@@ -374,8 +2297,192 @@ fn create_placemark(args: PlacemarkArgs) -> Kml<CoordValue> {
     })
 }
 
+/// Summarize a line consisting of `segments` like `120 points, 12.35 km`.
+fn line_summary<'a>(
+    segments: impl IntoIterator<Item = &'a [Waypoint]> + Clone,
+    options: &ConversionOptions,
+) -> String {
+    let points: usize = segments.clone().into_iter().map(<[_]>::len).sum();
+    let length_m: f64 = segments.into_iter().map(total_length_m).sum();
+    format!(
+        "{points} points, {}",
+        format_distance(length_m, options.units)
+    )
+}
+
+/// Select the text of the _Snippet_ of a placemark.
+///
+/// This is the beginning of the description without markup or otherwise the
+/// summary.
+fn snippet<T: CoordValue>(args: &PlacemarkArgs<T>) -> Option<String> {
+    let Some(ref description) = args.description else {
+        return args.summary.clone();
+    };
+
+    let mut snippet = strip_html(description)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    if snippet.chars().count() > SNIPPET_MAX_CHARS {
+        snippet = snippet.chars().take(SNIPPET_MAX_CHARS - 1).collect();
+        snippet.push('…');
+    }
+    Some(snippet)
+}
+
+/// Append the `line` to the `description` if there is one.
+fn append_line(mut description: String, line: Option<&str>) -> String {
+    if let Some(line) = line {
+        if !description.is_empty() && !description.ends_with('\n') {
+            description.push('\n');
+        }
+        writeln!(description, "{line}").unwrap();
+    }
+    description
+}
+
+/// Surround the `description` with the prefix and suffix of the `options`.
+fn add_affixes(description: String, options: &ConversionOptions) -> String {
+    let (prefix, suffix) = (&options.description_prefix, &options.description_suffix);
+    if prefix.is_none() && suffix.is_none() {
+        return description;
+    }
+
+    let mut text = String::new();
+    if let Some(prefix) = prefix {
+        writeln!(text, "{prefix}").unwrap();
+    }
+    text.push_str(&description);
+    if let Some(suffix) = suffix {
+        if !text.is_empty() && !text.ends_with('\n') {
+            text.push('\n');
+        }
+        text.push_str(suffix);
+    }
+    text
+}
+
+/// Render the description of a placemark as plain text.
+fn plain_description<T: CoordValue>(
+    args: &PlacemarkArgs<T>,
+    options: &ConversionOptions,
+) -> String {
+    let mut description = String::new();
+    if let Some(ref number) = args.number {
+        writeln!(description, "{}", number).unwrap();
+    }
+    if let Some(ref desc) = args.description {
+        writeln!(description, "{}", desc).unwrap();
+    }
+    if let Some(ref comment) = args.comment {
+        writeln!(description, "{}", comment).unwrap();
+    }
+    if let Some(ref time) = args.time {
+        writeln!(description, "Created {}", time).unwrap();
+    }
+    if let Some(ref source) = args.source {
+        writeln!(description, "Source: {}", source).unwrap();
+    }
+    if let Some(ref typ) = args.typ {
+        writeln!(description, "Type: {}", typ).unwrap();
+    }
+    if let Some(ref statistics) = args.statistics {
+        description.push_str(&format_statistics(statistics, options));
+    }
+
+    if description.is_empty() {
+        description
+    } else {
+        escape_description(description, options)
+    }
+}
+
+/// Collect the values of a placemark as labeled HTML table rows.
+///
+/// The name, description, and comment are only included if `with_text` is
+/// set.
+fn html_rows<T: CoordValue>(
+    args: &PlacemarkArgs<T>,
+    with_text: bool,
+    options: &ConversionOptions,
+) -> Vec<(&'static str, String)> {
+    let mut rows = vec![];
+    if with_text {
+        if let Some(ref name) = args.name {
+            rows.push(("Name", html::escape(name)));
+        }
+    }
+    if let Some(ref number) = args.number {
+        rows.push(("Number", html::escape(number)));
+    }
+    if with_text {
+        if let Some(ref description) = args.description {
+            rows.push((
+                "Description",
+                escape_description(description.clone(), options),
+            ));
+        }
+        if let Some(ref comment) = args.comment {
+            rows.push(("Comment", escape_description(comment.clone(), options)));
+        }
+    }
+    if let Some(position) = args.position {
+        let position = format_position(position, options.coordinate_display_precision);
+        rows.push(("Coordinates", html::escape(&position)));
+    }
+    if let Some(elevation) = args.elevation {
+        rows.push((
+            "Elevation",
+            html::escape(&format_elevation(elevation, options.units)),
+        ));
+    }
+    if let Some(ref time) = args.time {
+        rows.push(("Time", html::escape(time)));
+    }
+    if let Some(ref source) = args.source {
+        rows.push(("Source", html::escape(source)));
+    }
+    if let Some(ref typ) = args.typ {
+        rows.push(("Type", html::escape(typ)));
+    }
+    if !args.links.is_empty() {
+        let links: Vec<_> = args
+            .links
+            .iter()
+            .map(|link| html::link(&link.href, link.text.as_deref().unwrap_or(&link.href)))
+            .collect();
+        rows.push(("Links", links.join("<br>")));
+    }
+    if let Some(ref statistics) = args.statistics {
+        for (label, value) in statistics_rows(statistics, options) {
+            rows.push((label, html::escape(&value)));
+        }
+    }
+    rows
+}
+
+/// Treat markup in `description` according to the `options`.
+fn escape_description(description: String, options: &ConversionOptions) -> String {
+    match options.description_escape {
+        // These are handled when writing the KML data.
+        DescriptionEscape::None | DescriptionEscape::HtmlCdata => description,
+        DescriptionEscape::StripHtml => strip_html(&description),
+        #[cfg(feature = "ammonia")]
+        DescriptionEscape::SanitizeHtml => ammonia::clean(&description),
+    }
+}
+
+/// Format `time` as ISO 8601 string.
+///
+/// Times which cannot be formatted are skipped with a warning.
+fn format_time(time: Time) -> Option<String> {
+    time.format()
+        .map_err(|err| warn!("skipping time which cannot be formatted: {err}"))
+        .ok()
+}
+
 /// Create a simple KML element with `name` and `content`.
-fn simple_kelem(name: impl Into<String>, content: impl Into<String>) -> Kml<CoordValue> {
+fn simple_kelem<T: CoordValue>(name: impl Into<String>, content: impl Into<String>) -> Kml<T> {
     Kml::Element(simple_element(name, content))
 }
 
@@ -397,3 +2504,45 @@ fn atom_link(href: impl Into<String>) -> Element {
         ..Default::default()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_helpers::GpxBuilder;
+
+    /// Convert `gpx` with the default options and return the KML output.
+    fn convert_to_string(gpx: Gpx) -> String {
+        let mut sink = vec![];
+        let _ = convert_gpx(gpx, &mut sink, &Default::default()).expect("conversion failed");
+        String::from_utf8(sink).expect("KML data is not valid UTF-8")
+    }
+
+    #[test]
+    fn waypoints() {
+        let gpx = GpxBuilder::new()
+            .waypoint(48.858222, 2.2945)
+            .waypoint_named(41.89021, 12.492231, "Colosseum")
+            .build();
+        let kml = convert_to_string(gpx);
+
+        assert_eq!(kml.matches("<Placemark>").count(), 2);
+        assert!(kml.contains("<name>Colosseum</name>"));
+        assert!(kml.contains("<coordinates>12.492231,41.89021</coordinates>"));
+    }
+
+    #[test]
+    fn lines() {
+        let gpx = GpxBuilder::new()
+            .route(&[(0.0, 0.0), (1.0, 1.0)])
+            .track_segment(&[(0.0, 0.0), (1.0, 0.0)])
+            .track_segment(&[(1.0, 0.0), (1.0, 1.0)])
+            .track()
+            .track_segment(&[(2.0, 2.0), (3.0, 3.0)])
+            .build();
+        let kml = convert_to_string(gpx);
+
+        assert_eq!(kml.matches("<Placemark>").count(), 3);
+        assert_eq!(kml.matches("<LineString>").count(), 4);
+        assert_eq!(kml.matches("<MultiGeometry>").count(), 2);
+    }
+}
@@ -0,0 +1,41 @@
+// Copyright 2023 Viktor Reusch
+//
+// This file is part of gpx_kml_convert.
+//
+// gpx_kml_convert is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, either version 3 of the License, or (at your
+// option) any later version.
+//
+// gpx_kml_convert is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License
+// for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with gpx_kml_convert. If not, see <https://www.gnu.org/licenses/>.
+
+//! Macros for optional logging via the [`log`](https://docs.rs/log) crate.
+//!
+//! The macros forward to the respective `log` macros if the `logging` feature
+//! is enabled. Otherwise, they expand to code without any runtime effect.
+
+/// Log a debug message if the `logging` feature is enabled.
+macro_rules! debug {
+    ($($arg:tt)*) => {{
+        #[cfg(feature = "logging")]
+        log::debug!($($arg)*);
+        #[cfg(not(feature = "logging"))]
+        let _ = format_args!($($arg)*);
+    }};
+}
+
+/// Log a warning if the `logging` feature is enabled.
+macro_rules! warn {
+    ($($arg:tt)*) => {{
+        #[cfg(feature = "logging")]
+        log::warn!($($arg)*);
+        #[cfg(not(feature = "logging"))]
+        let _ = format_args!($($arg)*);
+    }};
+}
@@ -0,0 +1,98 @@
+// Copyright 2023 Viktor Reusch
+//
+// This file is part of gpx_kml_convert.
+//
+// gpx_kml_convert is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, either version 3 of the License, or (at your
+// option) any later version.
+//
+// gpx_kml_convert is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License
+// for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with gpx_kml_convert. If not, see <https://www.gnu.org/licenses/>.
+
+//! Extensions for the [OsmAnd](https://osmand.net) maps app.
+
+use kml::types::Element;
+
+use crate::{simple_element, util::normalize_activity_type};
+
+/// Namespace attribute for OsmAnd elements.
+pub(crate) const OSMAND_NAMESPACE: &str = "xmlns:osmand";
+/// URI of the OsmAnd namespace.
+pub(crate) const OSMAND_URI: &str = "https://osmand.net";
+/// Icon for symbols without a known OsmAnd equivalent.
+const DEFAULT_ICON: &str = "special_star";
+/// Color of the OsmAnd icons in `#rrggbb` notation.
+const COLOR: &str = "#d00d0d";
+/// Shape of the OsmAnd icon backgrounds.
+const BACKGROUND: &str = "circle";
+
+/// OsmAnd icons for common GPX symbols.
+///
+/// The symbols are normalized with [`normalize_activity_type`]. Most are
+/// taken from the symbols of Garmin devices.
+const ICONS: &[(&str, &str)] = &[
+    ("airport", "aeroway_aerodrome"),
+    ("bike trail", "highway_cycleway"),
+    ("campground", "tourism_camp_site"),
+    ("car", "shop_car"),
+    ("drinking water", "amenity_drinking_water"),
+    ("flag", "special_flag"),
+    ("flag, blue", "special_flag"),
+    ("flag, green", "special_flag"),
+    ("flag, red", "special_flag"),
+    ("gas station", "amenity_fuel"),
+    ("home", "special_house"),
+    ("information", "tourism_information"),
+    ("lodging", "tourism_hotel"),
+    ("medical facility", "amenity_hospital"),
+    ("parking area", "amenity_parking"),
+    ("picnic area", "tourism_picnic_site"),
+    ("residence", "special_house"),
+    ("restaurant", "amenity_restaurant"),
+    ("restroom", "amenity_toilets"),
+    ("scenic area", "tourism_viewpoint"),
+    ("shopping center", "shop_supermarket"),
+    ("summit", "natural_peak"),
+    ("trail head", "highway_trailhead"),
+];
+
+/// Look up the OsmAnd icon for the GPX `symbol`.
+fn icon(symbol: &str) -> &'static str {
+    let symbol = normalize_activity_type(symbol);
+    ICONS
+        .iter()
+        .find(|(s, _)| *s == symbol)
+        .map_or(DEFAULT_ICON, |(_, icon)| icon)
+}
+
+/// Create the OsmAnd elements for a waypoint with the GPX `symbol`.
+pub(crate) fn waypoint_elements(symbol: Option<&str>) -> Vec<Element> {
+    vec![
+        simple_element("osmand:icon", symbol.map_or(DEFAULT_ICON, icon)),
+        simple_element("osmand:color", COLOR),
+        simple_element("osmand:background", BACKGROUND),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn icons() {
+        assert_eq!(icon("Summit"), "natural_peak");
+        assert_eq!(icon("Flag, Blue"), "special_flag");
+        assert_eq!(icon("parking_area"), "amenity_parking");
+        assert_eq!(icon("Geocache"), DEFAULT_ICON);
+        assert!(
+            ICONS.windows(2).all(|w| w[0].0 < w[1].0),
+            "icons not sorted"
+        );
+    }
+}
@@ -0,0 +1,206 @@
+// Copyright 2023 Viktor Reusch
+//
+// This file is part of gpx_kml_convert.
+//
+// gpx_kml_convert is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, either version 3 of the License, or (at your
+// option) any later version.
+//
+// gpx_kml_convert is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License
+// for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with gpx_kml_convert. If not, see <https://www.gnu.org/licenses/>.
+
+//! Reporting of the conversion progress.
+
+use std::fmt::{self, Debug, Formatter};
+use std::sync::Arc;
+
+/// Receiver of progress updates during the conversion.
+///
+/// The progress is measured in converted features, i.e., waypoints, routes,
+/// and tracks. This is implemented for closures taking the number of
+/// converted features and the total number of features.
+pub trait ProgressReporter: Send + Sync {
+    /// Report that `converted` out of `total` features are converted.
+    fn report(&self, converted: u64, total: u64);
+
+    /// Report that the conversion of all features finished.
+    fn finish(&self) {}
+}
+
+impl<F: Fn(u64, u64) + Send + Sync> ProgressReporter for F {
+    fn report(&self, converted: u64, total: u64) {
+        self(converted, total);
+    }
+}
+
+#[cfg(feature = "indicatif")]
+impl ProgressReporter for indicatif::ProgressBar {
+    fn report(&self, converted: u64, total: u64) {
+        self.set_length(total);
+        self.set_position(converted);
+    }
+
+    fn finish(&self) {
+        indicatif::ProgressBar::finish(self);
+    }
+}
+
+/// Way of reporting the conversion progress.
+#[derive(Clone, Default)]
+// Variants may depend on enabled features.
+#[non_exhaustive]
+pub enum ProgressStyle {
+    /// Do not report any progress.
+    #[default]
+    None,
+    /// Pass the progress to a [`ProgressReporter`], e.g., a closure.
+    ///
+    /// # Example
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use gpx_kml_convert::{ConversionOptions, ProgressStyle};
+    /// #
+    /// let options = ConversionOptions {
+    ///     progress: ProgressStyle::Callback(Arc::new(|converted, total| {
+    ///         eprintln!("converted {converted} of {total} features");
+    ///     })),
+    ///     ..Default::default()
+    /// };
+    /// ```
+    Callback(Arc<dyn ProgressReporter>),
+    /// Display a progress bar of the
+    /// [`indicatif`](https://docs.rs/indicatif) crate on STDERR.
+    ///
+    /// The bar is hidden if STDERR is not a terminal.
+    #[cfg(feature = "indicatif")]
+    ProgressBar,
+}
+
+impl ProgressStyle {
+    /// Create the reporter for a conversion with this style.
+    pub(crate) fn reporter(&self) -> Option<Arc<dyn ProgressReporter>> {
+        match self {
+            Self::None => None,
+            Self::Callback(reporter) => Some(Arc::clone(reporter)),
+            #[cfg(feature = "indicatif")]
+            Self::ProgressBar => {
+                let bar = indicatif::ProgressBar::new(0).with_style(
+                    indicatif::ProgressStyle::with_template("{bar:40} {pos}/{len} features")
+                        .expect("progress template is invalid"),
+                );
+                Some(Arc::new(bar))
+            }
+        }
+    }
+}
+
+impl Debug for ProgressStyle {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::None => f.write_str("None"),
+            Self::Callback(_) => f.write_str("Callback(..)"),
+            #[cfg(feature = "indicatif")]
+            Self::ProgressBar => f.write_str("ProgressBar"),
+        }
+    }
+}
+
+impl PartialEq for ProgressStyle {
+    /// Callbacks are only equal if they share the same reporter.
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::None, Self::None) => true,
+            (Self::Callback(a), Self::Callback(b)) => Arc::ptr_eq(a, b),
+            #[cfg(feature = "indicatif")]
+            (Self::ProgressBar, Self::ProgressBar) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Progress of one conversion.
+pub(crate) struct Progress {
+    reporter: Option<Arc<dyn ProgressReporter>>,
+    converted: u64,
+    total: u64,
+}
+
+impl Progress {
+    /// Start reporting the conversion of `total` features in the given
+    /// `style`.
+    pub(crate) fn start(style: &ProgressStyle, total: usize) -> Self {
+        let progress = Self {
+            reporter: style.reporter(),
+            converted: 0,
+            total: total as u64,
+        };
+        progress.report();
+        progress
+    }
+
+    /// Count one more feature as converted.
+    pub(crate) fn advance(&mut self) {
+        self.converted += 1;
+        self.report();
+    }
+
+    /// Finish reporting.
+    pub(crate) fn finish(self) {
+        if let Some(reporter) = self.reporter {
+            reporter.finish();
+        }
+    }
+
+    /// Pass the current progress to the reporter.
+    fn report(&self) {
+        if let Some(ref reporter) = self.reporter {
+            reporter.report(self.converted, self.total);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn callback() {
+        let reports = Arc::new(Mutex::new(vec![]));
+        let style = ProgressStyle::Callback(Arc::new({
+            let reports = Arc::clone(&reports);
+            move |converted, total| reports.lock().unwrap().push((converted, total))
+        }));
+        assert_eq!(style, style.clone());
+        assert_ne!(style, ProgressStyle::None);
+
+        let mut progress = Progress::start(&style, 2);
+        progress.advance();
+        progress.advance();
+        progress.finish();
+        assert_eq!(*reports.lock().unwrap(), [(0, 2), (1, 2), (2, 2)]);
+    }
+
+    #[cfg(feature = "indicatif")]
+    #[test]
+    fn progress_bar() {
+        assert_eq!(ProgressStyle::ProgressBar, ProgressStyle::ProgressBar);
+        assert_eq!(format!("{:?}", ProgressStyle::ProgressBar), "ProgressBar");
+        assert!(ProgressStyle::ProgressBar.reporter().is_some());
+
+        let bar = indicatif::ProgressBar::hidden();
+        let style = ProgressStyle::Callback(Arc::new(bar.clone()));
+        let mut progress = Progress::start(&style, 2);
+        progress.advance();
+        assert_eq!((bar.position(), bar.length()), (1, Some(2)));
+        progress.advance();
+        progress.finish();
+        assert!(bar.is_finished());
+    }
+}
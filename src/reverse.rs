@@ -0,0 +1,326 @@
+// Copyright 2023 Viktor Reusch
+//
+// This file is part of gpx_kml_convert.
+//
+// gpx_kml_convert is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, either version 3 of the License, or (at your
+// option) any later version.
+//
+// gpx_kml_convert is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License
+// for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with gpx_kml_convert. If not, see <https://www.gnu.org/licenses/>.
+
+//! Conversion of KML structures back to GPX.
+//!
+//! This also allows validating how faithfully the GPX data survives a
+//! conversion to KML and back.
+
+use gpx::{Gpx, Link, Metadata, Route, Time, Track, TrackSegment, Waypoint};
+use kml::types::{Coord, Element, Geometry, Placemark};
+use kml::{Kml, KmlDocument, KmlReader};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+use crate::{convert_gpx, ConversionOptions, Error, GpxAccumulator};
+
+/// Report about how faithfully a conversion preserves the GPX data.
+///
+/// This is returned from [`validate_kml_roundtrip`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RoundtripReport {
+    /// Largest difference in degrees between the latitude or longitude of an
+    /// original point and its counterpart in the KML output.
+    pub max_coordinate_delta: f64,
+    /// Whether the KML output contains exactly one placemark per waypoint,
+    /// route, and track.
+    pub placemark_count_match: bool,
+    /// Fraction of the named waypoints, routes, and tracks whose name is
+    /// unchanged in the KML output.
+    ///
+    /// This is `1.0` if the GPX data contains no names.
+    pub name_preservation_rate: f64,
+}
+
+/// Convert the GPX data in `gpx_bytes` to KML and compare the result with the
+/// original.
+///
+/// The KML output is created with the default [`ConversionOptions`], parsed
+/// again, and converted back with [`kml_to_gpx_structs`]. Waypoints, routes,
+/// and tracks are then matched in order with the original ones. This is
+/// primarily meant for testing the conversion.
+///
+/// # Errors
+/// Fails if the GPX data is invalid, the conversion fails, or the KML output
+/// cannot be read back. The latter is reported as [`Error::InvalidKml`].
+///
+/// # Example
+/// ```
+/// # use gpx_kml_convert::validate_kml_roundtrip;
+/// #
+/// let source = r#"<?xml version="1.0" encoding="UTF-8"?>
+/// <gpx xmlns="http://www.topografix.com/GPX/1/1" version="1.1">
+///     <wpt lat="48.858222" lon="2.2945"><name>Eiffel Tower</name></wpt>
+/// </gpx>
+/// "#;
+///
+/// let report = validate_kml_roundtrip(source.as_bytes()).expect("roundtrip failed");
+/// assert!(report.placemark_count_match);
+/// assert!(report.max_coordinate_delta < 1e-9);
+/// assert_eq!(report.name_preservation_rate, 1.0);
+/// ```
+pub fn validate_kml_roundtrip(gpx_bytes: &[u8]) -> Result<RoundtripReport, Error> {
+    let original = gpx::read(gpx_bytes)?;
+    let mut kml = vec![];
+    let _ = convert_gpx(original.clone(), &mut kml, &ConversionOptions::default())?;
+    let document = match KmlReader::<_, f64>::from_reader(kml.as_slice()).read() {
+        Ok(Kml::KmlDocument(document)) => document,
+        Ok(_) => return Err(Error::InvalidKml("KML root missing".to_string())),
+        Err(err) => return Err(Error::InvalidKml(err.to_string())),
+    };
+    let converted = kml_to_gpx_structs(&document)?;
+
+    let placemark_count_match = original.waypoints.len() == converted.waypoints.len()
+        && original.routes.len() == converted.routes.len()
+        && original.tracks.len() == converted.tracks.len();
+
+    let mut max_coordinate_delta: f64 = 0.0;
+    let mut compare_points = |original: &[Waypoint], converted: &[Waypoint]| {
+        for (a, b) in original.iter().zip(converted) {
+            let (a, b) = (a.point(), b.point());
+            max_coordinate_delta = max_coordinate_delta
+                .max((a.x() - b.x()).abs())
+                .max((a.y() - b.y()).abs());
+        }
+    };
+    compare_points(&original.waypoints, &converted.waypoints);
+    for (a, b) in original.routes.iter().zip(&converted.routes) {
+        compare_points(&a.points, &b.points);
+    }
+    for (a, b) in original.tracks.iter().zip(&converted.tracks) {
+        for (a, b) in a.segments.iter().zip(&b.segments) {
+            compare_points(&a.points, &b.points);
+        }
+    }
+
+    let names = |gpx: &Gpx| -> Vec<Option<String>> {
+        let waypoints = gpx.waypoints.iter().map(|w| w.name.clone());
+        let routes = gpx.routes.iter().map(|r| r.name.clone());
+        let tracks = gpx.tracks.iter().map(|t| t.name.clone());
+        waypoints.chain(routes).chain(tracks).collect()
+    };
+    let (original_names, converted_names) = (names(&original), names(&converted));
+    let named = original_names.iter().flatten().count();
+    let preserved = original_names
+        .iter()
+        .zip(&converted_names)
+        .filter(|(a, b)| a.is_some() && a == b)
+        .count();
+    let name_preservation_rate = if named == 0 {
+        1.0
+    } else {
+        preserved as f64 / named as f64
+    };
+
+    Ok(RoundtripReport {
+        max_coordinate_delta,
+        placemark_count_match,
+        name_preservation_rate,
+    })
+}
+
+/// Convert the parsed `kml` document back to GPX data.
+///
+/// This reverses the conversion of [`convert`](crate::convert):
+///
+/// - Placemarks with a _Point_ become waypoints.
+/// - Placemarks with a _LineString_ become routes.
+/// - Placemarks with a _MultiGeometry_ become tracks with one segment per
+///   contained _LineString_.
+///
+/// Names, descriptions, Atom links, and _TimeStamps_ are kept. The GPX
+/// metadata is taken from the first KML _Document_. Placemarks with other
+/// geometries are skipped.
+///
+/// # Errors
+/// Fails with [`Error::InvalidKml`] if a _TimeStamp_ is not a valid
+/// [RFC 3339](https://www.rfc-editor.org/rfc/rfc3339) time.
+///
+/// # Example
+/// ```
+/// # use gpx_kml_convert::kml_to_gpx_structs;
+/// # use kml::{Kml, KmlReader};
+/// #
+/// let kml = r#"<kml xmlns="http://www.opengis.net/kml/2.2"><Document>
+///     <Placemark><name>Eiffel Tower</name><Point><coordinates>2.2945,48.858222</coordinates></Point></Placemark>
+/// </Document></kml>"#;
+/// let Kml::KmlDocument(document) = KmlReader::<_, f64>::from_string(kml).read().unwrap() else {
+///     panic!("KML root missing");
+/// };
+///
+/// let gpx = kml_to_gpx_structs(&document).expect("conversion failed");
+/// assert_eq!(gpx.waypoints[0].name.as_deref(), Some("Eiffel Tower"));
+/// ```
+pub fn kml_to_gpx_structs(kml: &KmlDocument<f64>) -> Result<Gpx, Error> {
+    let mut accumulator = GpxAccumulator::new();
+    let mut metadata = None;
+    for element in &kml.elements {
+        collect(element, &mut accumulator, &mut metadata)?;
+    }
+
+    let mut gpx = accumulator.into_gpx();
+    gpx.metadata = metadata;
+    Ok(gpx)
+}
+
+/// Recursively collect the features in `kml` into the `accumulator`.
+///
+/// The `metadata` is set from the first document encountered.
+fn collect(
+    kml: &Kml,
+    accumulator: &mut GpxAccumulator,
+    metadata: &mut Option<Metadata>,
+) -> Result<(), Error> {
+    match kml {
+        Kml::KmlDocument(document) => {
+            for element in &document.elements {
+                collect(element, accumulator, metadata)?;
+            }
+        }
+        Kml::Document { elements, .. } => {
+            if metadata.is_none() {
+                *metadata = Some(convert_metadata(elements)?);
+            }
+            for element in elements {
+                collect(element, accumulator, metadata)?;
+            }
+        }
+        Kml::Folder { elements, .. } => {
+            for element in elements {
+                collect(element, accumulator, metadata)?;
+            }
+        }
+        Kml::Placemark(placemark) => convert_placemark(placemark, accumulator)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Convert the direct `elements` of a KML _Document_ to GPX metadata.
+fn convert_metadata(elements: &[Kml]) -> Result<Metadata, Error> {
+    let elements: Vec<_> = elements
+        .iter()
+        .filter_map(|e| match e {
+            Kml::Element(element) => Some(element),
+            _ => None,
+        })
+        .collect();
+    let content = |name| {
+        elements
+            .iter()
+            .find(|e| e.name == name)
+            .and_then(|e| e.content.clone())
+    };
+
+    Ok(Metadata {
+        name: content("name"),
+        description: content("description"),
+        links: convert_links(elements.iter().copied()),
+        time: convert_timestamp(elements.iter().copied())?,
+        ..Default::default()
+    })
+}
+
+/// Convert a KML `placemark` and add it to the `accumulator`.
+fn convert_placemark(placemark: &Placemark, accumulator: &mut GpxAccumulator) -> Result<(), Error> {
+    let name = placemark.name.clone();
+    let description = placemark.description.clone();
+    let links = convert_links(&placemark.children);
+    match &placemark.geometry {
+        Some(Geometry::Point(point)) => {
+            let mut waypoint = convert_coord(&point.coord);
+            waypoint.name = name;
+            waypoint.description = description;
+            waypoint.links = links;
+            waypoint.time = convert_timestamp(&placemark.children)?;
+            accumulator.extend([waypoint]);
+        }
+        Some(Geometry::LineString(line)) => accumulator.extend([Route {
+            name,
+            description,
+            links,
+            points: line.coords.iter().map(convert_coord).collect(),
+            ..Default::default()
+        }]),
+        Some(Geometry::MultiGeometry(multi)) => {
+            let segments = multi
+                .geometries
+                .iter()
+                .filter_map(|geometry| match geometry {
+                    Geometry::LineString(line) => Some(TrackSegment {
+                        points: line.coords.iter().map(convert_coord).collect(),
+                    }),
+                    _ => {
+                        warn!("skipping unsupported geometry in track");
+                        None
+                    }
+                })
+                .collect();
+            accumulator.extend([Track {
+                name,
+                description,
+                links,
+                segments,
+                ..Default::default()
+            }]);
+        }
+        Some(_) => warn!("skipping placemark with unsupported geometry"),
+        None => {}
+    }
+    Ok(())
+}
+
+/// Convert a KML coordinate to a waypoint.
+fn convert_coord(coord: &Coord) -> Waypoint {
+    let mut waypoint = Waypoint::new((coord.x, coord.y).into());
+    waypoint.elevation = coord.z;
+    waypoint
+}
+
+/// Convert the `atom:link` elements among `children` to GPX links.
+///
+/// The KML reader strips the namespace prefix, so plain `link` elements are
+/// accepted, too.
+fn convert_links<'a>(children: impl IntoIterator<Item = &'a Element>) -> Vec<Link> {
+    children
+        .into_iter()
+        .filter(|e| e.name == "atom:link" || e.name == "link")
+        .filter_map(|e| e.attrs.get("href"))
+        .map(|href| Link {
+            href: href.clone(),
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// Convert the first _TimeStamp_ among `children` to a GPX time.
+fn convert_timestamp<'a>(
+    children: impl IntoIterator<Item = &'a Element>,
+) -> Result<Option<Time>, Error> {
+    let Some(when) = children
+        .into_iter()
+        .filter(|e| e.name == "TimeStamp")
+        .flat_map(|e| &e.children)
+        .find(|e| e.name == "when")
+        .and_then(|e| e.content.as_deref())
+    else {
+        return Ok(None);
+    };
+
+    OffsetDateTime::parse(when.trim(), &Rfc3339)
+        .map(|time| Some(time.into()))
+        .map_err(|err| Error::InvalidKml(format!("invalid time {when:?}: {err}")))
+}
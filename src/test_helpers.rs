@@ -0,0 +1,99 @@
+// Copyright 2023 Viktor Reusch
+//
+// This file is part of gpx_kml_convert.
+//
+// gpx_kml_convert is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, either version 3 of the License, or (at your
+// option) any later version.
+//
+// gpx_kml_convert is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License
+// for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with gpx_kml_convert. If not, see <https://www.gnu.org/licenses/>.
+
+//! Helpers for constructing test fixtures.
+
+use gpx::{Gpx, GpxVersion, Route, Track, TrackSegment, Waypoint};
+
+/// Builder for [`Gpx`] data.
+///
+/// All coordinates are given as `(latitude, longitude)` like in GPX files.
+pub(crate) struct GpxBuilder {
+    gpx: Gpx,
+}
+
+impl GpxBuilder {
+    /// Create a builder for an empty GPX 1.1 file.
+    pub(crate) fn new() -> Self {
+        Self {
+            gpx: Gpx {
+                version: GpxVersion::Gpx11,
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Add an unnamed waypoint.
+    pub(crate) fn waypoint(mut self, lat: f64, lon: f64) -> Self {
+        self.gpx.waypoints.push(waypoint(lat, lon));
+        self
+    }
+
+    /// Add a waypoint with `name`.
+    pub(crate) fn waypoint_named(mut self, lat: f64, lon: f64, name: &str) -> Self {
+        let mut waypoint = waypoint(lat, lon);
+        waypoint.name = Some(name.to_string());
+        self.gpx.waypoints.push(waypoint);
+        self
+    }
+
+    /// Add a segment with `coords` to the last track.
+    ///
+    /// A new track is created if there is none yet.
+    pub(crate) fn track_segment(mut self, coords: &[(f64, f64)]) -> Self {
+        if self.gpx.tracks.is_empty() {
+            self.gpx.tracks.push(Track::new());
+        }
+        let track = self.gpx.tracks.last_mut().unwrap();
+        track.segments.push(TrackSegment {
+            points: waypoints(coords),
+        });
+        self
+    }
+
+    /// Start a new track to which following segments are added.
+    pub(crate) fn track(mut self) -> Self {
+        self.gpx.tracks.push(Track::new());
+        self
+    }
+
+    /// Add a route with `coords`.
+    pub(crate) fn route(mut self, coords: &[(f64, f64)]) -> Self {
+        let mut route = Route::new();
+        route.points = waypoints(coords);
+        self.gpx.routes.push(route);
+        self
+    }
+
+    /// Finish building the GPX data.
+    pub(crate) fn build(self) -> Gpx {
+        self.gpx
+    }
+}
+
+/// Create a waypoint at `lat` and `lon`.
+pub(crate) fn waypoint(lat: f64, lon: f64) -> Waypoint {
+    Waypoint::new((lon, lat).into())
+}
+
+/// Create waypoints for all `coords`.
+fn waypoints(coords: &[(f64, f64)]) -> Vec<Waypoint> {
+    coords
+        .iter()
+        .map(|&(lat, lon)| waypoint(lat, lon))
+        .collect()
+}
@@ -0,0 +1,74 @@
+// Copyright 2023 Viktor Reusch
+//
+// This file is part of gpx_kml_convert.
+//
+// gpx_kml_convert is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, either version 3 of the License, or (at your
+// option) any later version.
+//
+// gpx_kml_convert is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License
+// for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with gpx_kml_convert. If not, see <https://www.gnu.org/licenses/>.
+
+//! Miscellaneous helper functions.
+
+/// Normalize a GPX activity _type_ for comparisons.
+///
+/// The result is lowercase, hyphens and underscores are replaced by spaces,
+/// and runs of whitespace are collapsed to a single space. Leading and
+/// trailing whitespace is removed.
+pub(crate) fn normalize_activity_type(s: &str) -> String {
+    s.split(|c: char| c.is_whitespace() || c == '-' || c == '_')
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Remove HTML tags like `<b>` or `</b>` from `text`.
+///
+/// A `<` which does not start a tag, e.g., in `a < b`, is kept.
+pub(crate) fn strip_html(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('<') {
+        stripped.push_str(&rest[..start]);
+        rest = &rest[start + 1..];
+        let is_tag = rest.starts_with(|c: char| c.is_ascii_alphabetic() || c == '/' || c == '!');
+        match rest.find('>').filter(|_| is_tag) {
+            Some(end) => rest = &rest[end + 1..],
+            None => stripped.push('<'),
+        }
+    }
+    stripped.push_str(rest);
+    stripped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize() {
+        assert_eq!(normalize_activity_type("Run"), "run");
+        assert_eq!(normalize_activity_type("  trail-Running "), "trail running");
+        assert_eq!(
+            normalize_activity_type("Mountain_Biking"),
+            "mountain biking"
+        );
+        assert_eq!(normalize_activity_type("road -_ cycling"), "road cycling");
+        assert_eq!(normalize_activity_type(" - "), "");
+    }
+
+    #[test]
+    fn strip() {
+        assert_eq!(strip_html("<p>A <b>bold</b> move</p>"), "A bold move");
+        assert_eq!(strip_html("a < b <br/>and<!-- x --> c>d"), "a < b and c>d");
+        assert_eq!(strip_html("unclosed <tag"), "unclosed <tag");
+    }
+}
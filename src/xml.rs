@@ -0,0 +1,219 @@
+// Copyright 2023 Viktor Reusch
+//
+// This file is part of gpx_kml_convert.
+//
+// gpx_kml_convert is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, either version 3 of the License, or (at your
+// option) any later version.
+//
+// gpx_kml_convert is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License
+// for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with gpx_kml_convert. If not, see <https://www.gnu.org/licenses/>.
+
+//! Rewriting of serialized XML data.
+//!
+//! The `kml` crate offers no control over how text is written, so some output
+//! variants require rewriting its output. Likewise, the `gpx` crate offers no
+//! control over how its input is interpreted.
+
+use std::io;
+
+use quick_xml::{
+    events::{BytesCData, BytesStart, BytesText, Event},
+    Reader, Writer,
+};
+
+/// Number of spaces per indentation level of pretty KML.
+const INDENT: usize = 2;
+
+/// Copy the `kml` data to `sink` with all descriptions as CDATA sections.
+pub(crate) fn cdata_descriptions(kml: &[u8], sink: impl io::Write) -> Result<(), quick_xml::Error> {
+    let mut reader = Reader::from_reader(kml);
+    let mut writer = Writer::new(sink);
+    let mut in_description = false;
+    loop {
+        match reader.read_event()? {
+            Event::Eof => break,
+            Event::Start(start) => {
+                in_description = start.name().as_ref() == b"description";
+                writer.write_event(Event::Start(start))?;
+            }
+            Event::Text(text) if in_description => {
+                let text = text.unescape()?;
+                // A CDATA section cannot contain `]]>`, so the text is split
+                // into several sections in between.
+                let mut rest = &*text;
+                while let Some(i) = rest.find("]]>") {
+                    writer.write_event(Event::CData(BytesCData::new(&rest[..i + 2])))?;
+                    rest = &rest[i + 2..];
+                }
+                writer.write_event(Event::CData(BytesCData::new(rest)))?;
+            }
+            event => {
+                in_description = false;
+                writer.write_event(event)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Copy the `gpx` data to `sink` with the `version` attribute of the root
+/// element replaced by `version`.
+pub(crate) fn force_gpx_version(
+    gpx: &[u8],
+    version: &str,
+    sink: impl io::Write,
+) -> Result<(), quick_xml::Error> {
+    let mut reader = Reader::from_reader(gpx);
+    let mut writer = Writer::new(sink);
+    let mut root_seen = false;
+    loop {
+        match reader.read_event()? {
+            Event::Eof => break,
+            Event::Start(start) if !root_seen => {
+                root_seen = true;
+                writer.write_event(Event::Start(with_version(&start, version)?))?;
+            }
+            Event::Empty(start) if !root_seen => {
+                root_seen = true;
+                writer.write_event(Event::Empty(with_version(&start, version)?))?;
+            }
+            event => writer.write_event(event)?,
+        }
+    }
+    Ok(())
+}
+
+/// Copy the `start` tag with its `version` attribute set to `version`.
+fn with_version(
+    start: &BytesStart,
+    version: &str,
+) -> Result<BytesStart<'static>, quick_xml::Error> {
+    let mut attributes = vec![];
+    for attribute in start.attributes() {
+        let attribute = attribute?;
+        if attribute.key.local_name().as_ref() != b"version" {
+            attributes.push(attribute);
+        }
+    }
+
+    let mut tag = start.to_owned();
+    tag.clear_attributes()
+        .extend_attributes(attributes)
+        .push_attribute(("version", version));
+    Ok(tag)
+}
+
+/// Copy the `kml` data to `sink` with indentation for human readers.
+///
+/// Each element starts on a new line and so does each tuple of coordinates.
+/// Elements without content are written as empty elements.
+pub(crate) fn indent(kml: &[u8], sink: impl io::Write) -> Result<(), quick_xml::Error> {
+    let mut reader = Reader::from_reader(kml);
+    let mut writer = Writer::new_with_indent(sink, b' ', INDENT);
+    let mut depth = 0;
+    let mut in_coordinates = false;
+    // The last start tag is held back to write an empty element instead if the
+    // end tag follows immediately.
+    let mut pending = None;
+    loop {
+        let event = reader.read_event()?;
+        if let Some(start) = pending.take() {
+            if matches!(event, Event::End(_)) {
+                depth -= 1;
+                writer.write_event(Event::Empty(start))?;
+                continue;
+            }
+            writer.write_event(Event::Start(start))?;
+        }
+
+        match event {
+            Event::Eof => break,
+            Event::Start(start) => {
+                depth += 1;
+                in_coordinates = start.name().as_ref() == b"coordinates";
+                pending = Some(start);
+            }
+            Event::End(end) => {
+                depth -= 1;
+                in_coordinates = false;
+                writer.write_event(Event::End(end))?;
+            }
+            Event::Text(text) if in_coordinates => {
+                let mut pretty = String::new();
+                for coord in text.unescape()?.split_whitespace() {
+                    pretty.push('\n');
+                    pretty.push_str(&" ".repeat(depth * INDENT));
+                    pretty.push_str(coord);
+                }
+                // Indent the closing tag to the level of the opening tag.
+                pretty.push('\n');
+                pretty.push_str(&" ".repeat((depth - 1) * INDENT));
+                writer.write_event(Event::Text(BytesText::new(&pretty)))?;
+            }
+            // Whitespace between elements is replaced by the indentation.
+            Event::Text(text) if text.iter().all(u8::is_ascii_whitespace) => {}
+            event => writer.write_event(event)?,
+        }
+    }
+    writer.into_inner().write_all(b"\n")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Rewrite `kml` with [`cdata_descriptions`].
+    fn rewrite(kml: &str) -> String {
+        let mut sink = vec![];
+        cdata_descriptions(kml.as_bytes(), &mut sink).unwrap();
+        String::from_utf8(sink).unwrap()
+    }
+
+    #[test]
+    fn cdata() {
+        assert_eq!(
+            rewrite(
+                "<a><description>&lt;b&gt;x&lt;/b&gt; &amp;</description><name>&lt;</name></a>"
+            ),
+            "<a><description><![CDATA[<b>x</b> &]]></description><name>&lt;</name></a>"
+        );
+        assert_eq!(
+            rewrite("<description>a]]&gt;b</description>"),
+            "<description><![CDATA[a]]]]><![CDATA[>b]]></description>"
+        );
+    }
+
+    #[test]
+    fn indentation() {
+        let kml = r#"<?xml version="1.0"?>
+<kml><Placemark><name>a &amp; b</name><link href="x"></link><LineString><coordinates>1,2,3 4,5,6</coordinates></LineString></Placemark></kml>
+"#;
+        let mut sink = vec![];
+        indent(kml.as_bytes(), &mut sink).unwrap();
+        assert_eq!(
+            String::from_utf8(sink).unwrap(),
+            r#"<?xml version="1.0"?>
+<kml>
+  <Placemark>
+    <name>a &amp; b</name>
+    <link href="x"/>
+    <LineString>
+      <coordinates>
+        1,2,3
+        4,5,6
+      </coordinates>
+    </LineString>
+  </Placemark>
+</kml>
+"#
+        );
+    }
+}
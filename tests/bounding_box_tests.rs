@@ -0,0 +1,117 @@
+// Copyright 2023 Viktor Reusch
+//
+// This file is part of gpx_kml_convert.
+//
+// gpx_kml_convert is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, either version 3 of the License, or (at your
+// option) any later version.
+//
+// gpx_kml_convert is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License
+// for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with gpx_kml_convert. If not, see <https://www.gnu.org/licenses/>.
+
+//! Tests for the [`BoundingBox`].
+
+use gpx_kml_convert::BoundingBox;
+use kml::types::Coord;
+
+/// Box around Switzerland.
+const SWITZERLAND: BoundingBox = BoundingBox {
+    min_lat: 45.8,
+    max_lat: 47.8,
+    min_lon: 5.9,
+    max_lon: 10.5,
+};
+
+#[test]
+fn empty() {
+    let bbox = BoundingBox::default();
+    assert!(bbox.is_empty());
+    assert!(!bbox.contains(0.0, 0.0));
+    assert_eq!(bbox.to_string(), "empty");
+}
+
+#[test]
+fn contains() {
+    assert!(SWITZERLAND.contains(46.9, 7.4));
+    assert!(SWITZERLAND.contains(45.8, 10.5));
+    assert!(!SWITZERLAND.contains(48.9, 2.3));
+    assert!(!SWITZERLAND.contains(46.9, 11.0));
+}
+
+#[test]
+fn display() {
+    assert_eq!(SWITZERLAND.to_string(), "N45.8°–N47.8°, E5.9°–E10.5°");
+
+    let americas = BoundingBox {
+        min_lat: -55.98,
+        max_lat: 71.4,
+        min_lon: -168.1,
+        max_lon: -34.8,
+    };
+    assert_eq!(americas.to_string(), "S56.0°–N71.4°, W168.1°–W34.8°");
+}
+
+#[test]
+fn expand() {
+    let mut bbox = BoundingBox::default();
+    bbox.expand(46.9, 7.4);
+    assert!(!bbox.is_empty());
+    assert!(bbox.contains(46.9, 7.4));
+
+    bbox.expand(47.4, 8.5);
+    bbox.expand(46.0, 8.9);
+    assert_eq!(
+        bbox,
+        BoundingBox {
+            min_lat: 46.0,
+            max_lat: 47.4,
+            min_lon: 7.4,
+            max_lon: 8.9,
+        }
+    );
+}
+
+#[test]
+fn union() {
+    let north = BoundingBox {
+        min_lat: 50.0,
+        max_lat: 51.0,
+        min_lon: 4.0,
+        max_lon: 6.0,
+    };
+    assert_eq!(
+        SWITZERLAND.union(&north),
+        BoundingBox {
+            min_lat: 45.8,
+            max_lat: 51.0,
+            min_lon: 4.0,
+            max_lon: 10.5,
+        }
+    );
+    assert_eq!(SWITZERLAND.union(&BoundingBox::default()), SWITZERLAND);
+}
+
+#[test]
+fn from_coords() {
+    assert_eq!(BoundingBox::from_coords(&[]), None);
+
+    let coords = [
+        Coord::new(7.4, 46.9, None),
+        Coord::new(8.5, 47.4, Some(400.0)),
+    ];
+    assert_eq!(
+        BoundingBox::from_coords(&coords),
+        Some(BoundingBox {
+            min_lat: 46.9,
+            max_lat: 47.4,
+            min_lon: 7.4,
+            max_lon: 8.5,
+        })
+    );
+}
@@ -0,0 +1,98 @@
+// Copyright 2023 Viktor Reusch
+//
+// This file is part of gpx_kml_convert.
+//
+// gpx_kml_convert is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, either version 3 of the License, or (at your
+// option) any later version.
+//
+// gpx_kml_convert is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License
+// for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with gpx_kml_convert. If not, see <https://www.gnu.org/licenses/>.
+
+//! Tests for the messages of [`gpx_kml_convert::Error`].
+
+use gpx::errors::GpxError;
+use std::fs::File;
+
+use gpx_kml_convert::{convert, convert_with_options, ConversionOptions, Error};
+
+#[test]
+fn gpx_error() {
+    let err = Error::from(GpxError::MissingOpeningTag("gpx"));
+    assert_eq!(
+        format!("{err}"),
+        "reading GPX failed: missing opening tag in `gpx`"
+    );
+    assert!(format!("{err:?}").contains("Gpx(MissingOpeningTag(\"gpx\"))"));
+}
+
+#[test]
+fn gpx_error_from_convert() {
+    let err = convert("<gpx>".as_bytes(), vec![]).expect_err("invalid GPX was accepted");
+    assert!(matches!(err, Error::Gpx(_)));
+    assert!(format!("{err}").starts_with("reading GPX failed: "));
+}
+
+#[test]
+fn kml_error() {
+    let err = Error::from(kml::Error::InvalidKmlElement("foo".to_string()));
+    assert_eq!(
+        format!("{err}"),
+        "writing KML failed: Invalid KML element: foo"
+    );
+    assert!(format!("{err:?}").contains("Kml(InvalidKmlElement(\"foo\"))"));
+}
+
+#[test]
+fn empty_input() {
+    let options = ConversionOptions {
+        require_content: true,
+        ..Default::default()
+    };
+    let source = File::open("./resources/metadata_only.gpx").expect("resource not found");
+    let mut sink = vec![];
+    let err =
+        convert_with_options(source, &mut sink, &options).expect_err("empty GPX was accepted");
+    assert!(matches!(err, Error::EmptyInput));
+    assert_eq!(
+        format!("{err}"),
+        "GPX contains no waypoints, routes, or tracks"
+    );
+    assert!(sink.is_empty());
+
+    let source = File::open("./resources/complete.gpx").expect("resource not found");
+    let _ = convert_with_options(source, vec![], &options).expect("conversion failed");
+}
+
+#[test]
+fn io_error() {
+    let err = Error::from(std::io::Error::other("disk on fire"));
+    assert_eq!(format!("{err}"), "I/O failed: disk on fire");
+    assert!(matches!(err, Error::Io(_)));
+}
+
+/// Writer failing on every write.
+struct FailingWriter;
+
+impl std::io::Write for FailingWriter {
+    fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+        Err(std::io::Error::other("disk on fire"))
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn failing_sink() {
+    let source = File::open("./resources/complete.gpx").expect("resource not found");
+    let err = convert(source, FailingWriter).expect_err("failing sink was accepted");
+    assert!(matches!(err, Error::Io(err) if err.to_string() == "disk on fire"));
+}
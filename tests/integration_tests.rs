@@ -0,0 +1,486 @@
+// Copyright 2023 Viktor Reusch
+//
+// This file is part of gpx_kml_convert.
+//
+// gpx_kml_convert is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, either version 3 of the License, or (at your
+// option) any later version.
+//
+// gpx_kml_convert is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License
+// for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with gpx_kml_convert. If not, see <https://www.gnu.org/licenses/>.
+
+//! Integration tests converting the GPX files in `resources/`.
+
+use std::{fs::File, path::Path};
+
+use gpx_kml_convert::{
+    convert, convert_to_multi_document, convert_to_pretty_kml, diff_gpx_kml, gpx_statistics,
+    gpx_summary_to_kml, ConversionOptions, DiffItem,
+};
+use kml::{
+    types::{Geometry, Placemark},
+    Kml, KmlReader,
+};
+
+const RESOURCES: &str = "./resources/";
+const XML_HEAD: &str = r#"<?xml version="1.0" encoding="UTF-8"?>"#;
+
+/// Convert the GPX file `name` from the resources and return the KML output.
+fn convert_resource(name: &str) -> String {
+    let source = File::open(Path::new(RESOURCES).join(name)).expect("resource not found");
+    let mut sink = vec![];
+    convert(source, &mut sink).expect("conversion failed");
+    String::from_utf8(sink).expect("KML data is not valid UTF-8")
+}
+
+/// Convert the GPX data in `source` and return the KML output.
+fn convert_str(source: &str) -> String {
+    let mut sink = vec![];
+    convert(source.as_bytes(), &mut sink).expect("conversion failed");
+    String::from_utf8(sink).expect("KML data is not valid UTF-8")
+}
+
+/// Parse the KML output with the `kml` crate.
+fn parse(kml: &str) -> Kml {
+    KmlReader::<_, f64>::from_string(kml)
+        .read()
+        .expect("KML output could not be parsed")
+}
+
+/// Recursively collect all placemarks contained in `kml`.
+fn placemarks(kml: &Kml) -> Vec<&Placemark> {
+    match kml {
+        Kml::KmlDocument(document) => document.elements.iter().flat_map(placemarks).collect(),
+        Kml::Document { elements, .. } | Kml::Folder { elements, .. } => {
+            elements.iter().flat_map(placemarks).collect()
+        }
+        Kml::Placemark(placemark) => vec![placemark],
+        _ => vec![],
+    }
+}
+
+/// Count the _LineStrings_ contained in `geometry`.
+fn count_line_strings(geometry: &Geometry) -> usize {
+    match geometry {
+        Geometry::LineString(_) => 1,
+        Geometry::MultiGeometry(multi) => multi.geometries.iter().map(count_line_strings).sum(),
+        _ => 0,
+    }
+}
+
+#[test]
+fn complete() {
+    let kml = convert_resource("complete.gpx");
+
+    assert_eq!(kml.lines().next(), Some(XML_HEAD));
+    assert!(kml.contains("<Document>"));
+
+    let parsed = parse(&kml);
+    let placemarks = placemarks(&parsed);
+    let names: Vec<_> = placemarks
+        .iter()
+        .filter_map(|p| p.name.as_deref())
+        .collect();
+    let gpx = gpx::read(File::open(Path::new(RESOURCES).join("complete.gpx")).unwrap()).unwrap();
+    for waypoint in gpx.waypoints {
+        let name = waypoint.name.expect("waypoint without name");
+        assert!(names.contains(&name.as_str()), "{name} missing");
+    }
+
+    let line_strings: usize = placemarks
+        .iter()
+        .filter_map(|p| p.geometry.as_ref())
+        .map(count_line_strings)
+        .sum();
+    // One route and a track with two segments.
+    assert_eq!(line_strings, 3);
+}
+
+#[test]
+fn empty() {
+    let kml = convert_resource("empty.gpx");
+
+    assert!(kml.starts_with(XML_HEAD));
+    assert!(kml.contains("<Document>"));
+    assert!(kml.contains("</Document>"));
+    assert!(!kml.contains("<Placemark"));
+    assert!(placemarks(&parse(&kml)).is_empty());
+}
+
+#[test]
+fn metadata_only() {
+    let kml = convert_resource("metadata_only.gpx");
+
+    assert!(placemarks(&parse(&kml)).is_empty());
+    for expected in [
+        "<name>Metadata Only</name>",
+        "This file contains metadata but no features.",
+        "Jane Doe &lt;jane@example.org&gt;",
+        "<atom:email>jane@example.org</atom:email>",
+        r#"href="https://example.org/jane""#,
+        r#"href="https://example.org/metadata""#,
+        "Created 2023-01-02T03:04:05",
+        "by gpx_kml_convert",
+        "Keywords: metadata,only",
+        "Copyright vilaureu 2023 under http://creativecommons.org/publicdomain/zero/1.0/",
+    ] {
+        assert!(kml.contains(expected), "{expected} missing");
+    }
+}
+
+#[test]
+fn coordinate_order() {
+    let source = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx xmlns="http://www.topografix.com/GPX/1/1" version="1.1">
+    <wpt lat="48.858222" lon="2.2945"><ele>30</ele></wpt>
+</gpx>
+"#;
+    let kml = convert_str(source);
+
+    // KML expects longitude, latitude, and altitude in this order.
+    assert!(kml.contains("<coordinates>2.2945,48.858222,30</coordinates>"));
+    assert!(!kml.contains("48.858222,2.2945"));
+}
+
+#[test]
+fn xml_injection() {
+    let names = ["<script>alert(1)</script>", "]]>", "<![CDATA[", "&amp; \"'"];
+    let mut source = String::from(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx xmlns="http://www.topografix.com/GPX/1/1" version="1.1">"#,
+    );
+    for name in names {
+        let escaped = name
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;");
+        source.push_str(&format!(
+            r#"<wpt lat="0" lon="0"><name>{escaped}</name><desc>{escaped}</desc></wpt>"#
+        ));
+    }
+    source.push_str("</gpx>");
+
+    let kml = convert_str(&source);
+    assert!(!kml.contains("<script>"));
+
+    let parsed = parse(&kml);
+    let placemarks = placemarks(&parsed);
+    assert_eq!(placemarks.len(), names.len());
+    for (placemark, name) in placemarks.iter().zip(names) {
+        assert_eq!(placemark.name.as_deref(), Some(name));
+    }
+}
+
+#[test]
+fn long_strings() {
+    let name = "n".repeat(10_000);
+    let description = "d".repeat(100_000);
+    let source = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx xmlns="http://www.topografix.com/GPX/1/1" version="1.1">
+    <wpt lat="0" lon="0"><name>{name}</name><desc>{description}</desc></wpt>
+</gpx>
+"#
+    );
+    let kml = convert_str(&source);
+
+    let parsed = parse(&kml);
+    let placemarks = placemarks(&parsed);
+    assert_eq!(placemarks.len(), 1);
+    assert_eq!(placemarks[0].name.as_ref(), Some(&name));
+    assert!(placemarks[0]
+        .description
+        .as_ref()
+        .expect("description missing")
+        .contains(&description));
+}
+
+#[test]
+fn unicode() {
+    let kml = convert_resource("unicode.gpx");
+
+    for expected in [
+        "地图 🗺️",
+        "长城徒步",
+        "رحلة إلى الجبال",
+        "Zoë Ångström",
+        "🥾,⛰️,e\u{301}",
+        "慕田峪 🏯",
+        "Ślęża – Ṩ – ﷽",
+        "مسار الجبل 🚶",
+        "A\u{30a} n\u{303} o\u{308}",
+    ] {
+        assert!(kml.contains(expected), "{expected} missing");
+    }
+}
+
+#[test]
+fn multi_document() {
+    let sources = ["complete.gpx", "metadata_only.gpx"]
+        .map(|name| File::open(Path::new(RESOURCES).join(name)).expect("resource not found"));
+    let mut sink = vec![];
+    let reports = convert_to_multi_document(sources, &mut sink, &ConversionOptions::default())
+        .expect("conversion failed");
+    assert_eq!(reports.len(), 2);
+    assert!(!reports[0].bounding_box.is_empty());
+    assert!(reports[1].bounding_box.is_empty());
+
+    let kml = String::from_utf8(sink).expect("KML data is not valid UTF-8");
+    assert_eq!(kml.matches(XML_HEAD).count(), 1);
+    let Kml::KmlDocument(document) = parse(&kml) else {
+        panic!("KML root missing");
+    };
+    let names: Vec<_> = document
+        .elements
+        .iter()
+        .map(|element| match element {
+            Kml::Document { elements, .. } => elements.iter().find_map(|e| match e {
+                Kml::Element(e) if e.name == "name" => e.content.as_deref(),
+                _ => None,
+            }),
+            _ => panic!("unexpected element {element:?}"),
+        })
+        .collect();
+    assert_eq!(names.len(), 2);
+    assert_eq!(names[1], Some("Metadata Only"));
+
+    let invalid = [&b"<gpx>"[..]];
+    assert!(convert_to_multi_document(invalid, vec![], &ConversionOptions::default()).is_err());
+}
+
+#[test]
+fn pretty() {
+    let source = File::open(Path::new(RESOURCES).join("complete.gpx")).unwrap();
+    let pretty = convert_to_pretty_kml(source).expect("conversion failed");
+    assert!(pretty.starts_with(XML_HEAD));
+    assert!(pretty.contains("\n  <Document>\n    <open>1</open>\n"));
+    assert_eq!(parse(&pretty), parse(&convert_resource("complete.gpx")));
+}
+
+#[test]
+fn statistics() {
+    let gpx = gpx::read(File::open(Path::new(RESOURCES).join("complete.gpx")).unwrap()).unwrap();
+    let statistics = gpx_statistics(&gpx);
+    assert_eq!(statistics.waypoint_count, 1);
+    assert_eq!(statistics.route_count, 1);
+    assert_eq!(statistics.track_count, 1);
+    assert_eq!(statistics.total_route_points, 3);
+    assert_eq!(statistics.total_track_points, 6);
+    assert!(statistics.has_elevation);
+    assert!(statistics.has_timestamps);
+    let bbox = statistics.bounding_box.expect("bounding box missing");
+    assert!(bbox.contains(48.858222, 2.2945));
+    assert!(bbox.contains(41.89046, 12.49122));
+
+    let gpx = gpx::read(File::open(Path::new(RESOURCES).join("empty.gpx")).unwrap()).unwrap();
+    let statistics = gpx_statistics(&gpx);
+    assert_eq!(statistics.waypoint_count + statistics.total_track_points, 0);
+    assert!(!statistics.has_elevation);
+    assert_eq!(statistics.bounding_box, None);
+}
+
+#[test]
+fn summary() {
+    let source = File::open(Path::new(RESOURCES).join("complete.gpx")).unwrap();
+    let mut sink = vec![];
+    gpx_summary_to_kml(source, &mut sink).expect("conversion failed");
+    let kml = String::from_utf8(sink).unwrap();
+
+    let parsed = parse(&kml);
+    let placemarks = placemarks(&parsed);
+    assert_eq!(placemarks.len(), 1);
+    assert_eq!(placemarks[0].name.as_deref(), Some("Complete"));
+    let description = placemarks[0].description.as_deref().unwrap();
+    assert!(description.contains("Waypoints: 1\nRoutes: 1 (3 points)\nTracks: 1 (6 points)\n"));
+    assert!(description.contains("Elevation: yes\nTimestamps: yes\n"));
+    let Some(Geometry::Point(point)) = &placemarks[0].geometry else {
+        panic!("point missing");
+    };
+    assert!(point.coord.y > 41.0 && point.coord.y < 49.0);
+
+    let source = File::open(Path::new(RESOURCES).join("empty.gpx")).unwrap();
+    let mut sink = vec![];
+    gpx_summary_to_kml(source, &mut sink).expect("conversion failed");
+    let kml = String::from_utf8(sink).unwrap();
+    assert!(kml.contains("Waypoints: 0"));
+    assert!(!kml.contains("<Point>"));
+}
+
+#[cfg(feature = "uuid")]
+#[test]
+fn conversion_id() {
+    let convert = || {
+        let source = File::open(Path::new(RESOURCES).join("complete.gpx")).unwrap();
+        let mut sink = vec![];
+        let (report, id) =
+            gpx_kml_convert::convert_with_id(source, &mut sink, &ConversionOptions::default())
+                .expect("conversion failed");
+        assert_eq!(report.id, Some(id));
+        (String::from_utf8(sink).unwrap(), id)
+    };
+
+    let (kml, id) = convert();
+    let comment = format!("<!-- Conversion-ID: {id} -->");
+    assert_eq!(kml.lines().nth(1), Some(comment.as_str()));
+    // The reader of the `kml` crate does not support comments.
+    assert_eq!(placemarks(&parse(&kml.replace(&comment, ""))).len(), 3);
+    assert_ne!(convert().1, id);
+}
+
+#[cfg(feature = "logging")]
+#[test]
+fn logging() {
+    use std::sync::Mutex;
+
+    /// Logger collecting the messages of all records.
+    struct Collector(Mutex<Vec<String>>);
+
+    impl log::Log for Collector {
+        fn enabled(&self, _: &log::Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record) {
+            self.0.lock().unwrap().push(record.args().to_string());
+        }
+
+        fn flush(&self) {}
+    }
+
+    static COLLECTOR: Collector = Collector(Mutex::new(vec![]));
+    log::set_logger(&COLLECTOR).expect("logger already set");
+    log::set_max_level(log::LevelFilter::Debug);
+
+    convert_resource("complete.gpx");
+    // Other tests may log concurrently, so only look for the expected record.
+    assert!(COLLECTOR
+        .0
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|message| message == "KML document written"));
+}
+
+#[cfg(feature = "slog")]
+#[test]
+fn structured_logging() {
+    use std::sync::{Arc, Mutex};
+
+    /// Message and keys of a record.
+    type Entry = (String, Vec<String>);
+
+    /// Drain collecting the messages and keys of all records.
+    #[derive(Clone, Default)]
+    struct Collector(Arc<Mutex<Vec<Entry>>>);
+
+    impl slog::Drain for Collector {
+        type Ok = ();
+        type Err = slog::Never;
+
+        fn log(&self, record: &slog::Record, _: &slog::OwnedKVList) -> Result<(), slog::Never> {
+            /// Serializer collecting the keys of key-value pairs.
+            struct Keys(Vec<String>);
+
+            impl slog::Serializer for Keys {
+                fn emit_arguments(
+                    &mut self,
+                    key: slog::Key,
+                    _: &std::fmt::Arguments,
+                ) -> slog::Result {
+                    self.0.push(key.to_string());
+                    Ok(())
+                }
+            }
+
+            let mut keys = Keys(vec![]);
+            slog::KV::serialize(&record.kv(), record, &mut keys).unwrap();
+            let message = record.msg().to_string();
+            self.0.lock().unwrap().push((message, keys.0));
+            Ok(())
+        }
+    }
+
+    let collector = Collector::default();
+    let logger = slog::Logger::root(collector.clone(), slog::o!());
+    let source = File::open(Path::new(RESOURCES).join("complete.gpx")).unwrap();
+    let report = gpx_kml_convert::convert_with_logger(
+        source,
+        vec![],
+        &ConversionOptions::default(),
+        &logger,
+    )
+    .expect("conversion failed");
+    assert_eq!(report.track_count, 1);
+
+    let (message, keys) = collector.0.lock().unwrap().pop().expect("no record");
+    assert_eq!(message, "conversion finished");
+    assert!(keys.iter().any(|key| key == "tracks_count"));
+    assert!(keys.iter().any(|key| key == "elapsed_ms"));
+
+    assert!(gpx_kml_convert::convert_with_logger(
+        &b"<gpx>"[..],
+        vec![],
+        &ConversionOptions::default(),
+        &logger,
+    )
+    .is_err());
+    let (message, keys) = collector.0.lock().unwrap().pop().expect("no record");
+    assert_eq!(message, "conversion failed");
+    assert!(keys.iter().any(|key| key == "error"));
+}
+
+#[test]
+fn diff() {
+    let source = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx xmlns="http://www.topografix.com/GPX/1/1" version="1.1">
+    <wpt lat="47.0" lon="11.0"><ele>812</ele><sym>Summit</sym></wpt>
+    <wpt lat="47.1" lon="11.1"><ele>600</ele></wpt>
+    <trk><trkseg>
+        <trkpt lat="47.0" lon="11.0"><ele>812</ele><time>2023-06-01T08:00:00Z</time></trkpt>
+        <trkpt lat="47.1" lon="11.1"><ele>600</ele><time>2023-06-01T09:00:00Z</time></trkpt>
+    </trkseg></trk>
+</gpx>
+"#;
+    let gpx = gpx::read(source.as_bytes()).unwrap();
+    let Kml::KmlDocument(mut document) = parse(&convert_str(source)) else {
+        panic!("KML root missing");
+    };
+    assert_eq!(
+        diff_gpx_kml(&gpx, &document),
+        [
+            DiffItem::WaypointSymbolLost {
+                waypoint_index: 0,
+                sym: "Summit".to_string()
+            },
+            DiffItem::TrackTimestampLost { track_index: 0 },
+        ]
+    );
+
+    let Kml::Document { elements, .. } = &mut document.elements[0] else {
+        panic!("document missing");
+    };
+    let point = elements
+        .iter_mut()
+        .filter_map(|e| match e {
+            Kml::Placemark(Placemark {
+                geometry: Some(Geometry::Point(point)),
+                ..
+            }) => Some(point),
+            _ => None,
+        })
+        .nth(1)
+        .expect("point missing");
+    point.coord.z = Some(590.0);
+    assert!(
+        diff_gpx_kml(&gpx, &document).contains(&DiffItem::ElevationDiscrepancy {
+            feature_path: "waypoints[1]".to_string(),
+            delta_m: 10.0
+        })
+    );
+}
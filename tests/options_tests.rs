@@ -0,0 +1,910 @@
+// Copyright 2023 Viktor Reusch
+//
+// This file is part of gpx_kml_convert.
+//
+// gpx_kml_convert is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, either version 3 of the License, or (at your
+// option) any later version.
+//
+// gpx_kml_convert is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License
+// for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with gpx_kml_convert. If not, see <https://www.gnu.org/licenses/>.
+
+//! Tests for the [`ConversionOptions`].
+
+use std::{fs::File, io::ErrorKind, path::Path, time::Duration};
+
+use gpx_kml_convert::{
+    convert, convert_to_multi_document, convert_with_options, BoundingBox, ConversionOptions,
+    CoordinateType, DescriptionEscape, DescriptionFormat, DistanceUnits, Error, InputFormat,
+    Warning,
+};
+use kml::{Kml, KmlReader};
+
+const RESOURCES: &str = "./resources/";
+
+/// Convert the GPX file `name` from the resources with `options`.
+fn convert_resource(name: &str, options: &ConversionOptions) -> String {
+    let source = File::open(Path::new(RESOURCES).join(name)).expect("resource not found");
+    let mut sink = vec![];
+    let _ = convert_with_options(source, &mut sink, options).expect("conversion failed");
+    String::from_utf8(sink).expect("KML data is not valid UTF-8")
+}
+
+/// Convert the GPX data in `source` with `options`.
+fn convert_str(source: &str, options: &ConversionOptions) -> String {
+    let mut sink = vec![];
+    let _ = convert_with_options(source.as_bytes(), &mut sink, options).expect("conversion failed");
+    String::from_utf8(sink).expect("KML data is not valid UTF-8")
+}
+
+/// Parse the KML output with the `kml` crate.
+fn parse(kml: &str) -> Kml {
+    KmlReader::<_, f64>::from_string(kml)
+        .read()
+        .expect("KML output could not be parsed")
+}
+
+#[test]
+fn default_matches_convert() {
+    let source = File::open(Path::new(RESOURCES).join("complete.gpx")).unwrap();
+    let mut sink = vec![];
+    convert(source, &mut sink).expect("conversion failed");
+    let expected = String::from_utf8(sink).unwrap();
+    let actual = convert_resource("complete.gpx", &ConversionOptions::default());
+
+    // Compare the parsed documents because the order of attributes may vary.
+    assert_eq!(parse(&actual), parse(&expected));
+}
+
+#[test]
+fn activity_colors() {
+    let options = ConversionOptions {
+        activity_colors: [("hiking tour".to_string(), "ff0000ff".to_string())].into(),
+        ..Default::default()
+    };
+    let kml = convert_resource("complete.gpx", &options);
+
+    // Only the track with type "Hiking Tour" is colored.
+    assert_eq!(
+        kml.matches("<Style><LineStyle><color>ff0000ff</color></LineStyle></Style>")
+            .count(),
+        1
+    );
+}
+
+#[test]
+fn exclude_atom_elements() {
+    let options = ConversionOptions {
+        include_atom_elements: false,
+        ..Default::default()
+    };
+    let kml = convert_resource("complete.gpx", &options);
+
+    assert!(!kml.contains("atom"));
+}
+
+#[test]
+fn bbox_filter() {
+    // Covers one track point in Switzerland and one point of the route.
+    let options = ConversionOptions {
+        bbox_filter: Some(BoundingBox {
+            min_lat: 41.8,
+            max_lat: 47.0,
+            min_lon: 8.0,
+            max_lon: 12.492,
+        }),
+        ..Default::default()
+    };
+    let kml = convert_resource("complete.gpx", &options);
+
+    assert!(!kml.contains("Eiffel Tower"));
+    assert!(kml.contains("12.49122,41.89046,53"));
+    assert!(kml.contains("8.7441667,46.613333,2572"));
+    assert!(!kml.contains("12.49329"));
+    assert!(!kml.contains("12.339722"));
+    assert!(!kml.contains("7.1899"));
+}
+
+#[test]
+fn statistics() {
+    let options = ConversionOptions {
+        include_statistics: true,
+        ..Default::default()
+    };
+    let kml = convert_resource("complete.gpx", &options);
+    assert!(kml.contains("Length: 0.30 km\nElevation gain: 0 m\nElevation loss: 1 m\n"));
+    assert!(kml.contains("Elevation gain: 922 m\nElevation loss: 577 m\n"));
+    assert!(kml.contains("Start: 41.8905°N, 12.4912°E\nEnd: 41.8896°N, 12.4926°E\n"));
+
+    let options = ConversionOptions {
+        units: DistanceUnits::Imperial,
+        ..options
+    };
+    let kml = convert_resource("complete.gpx", &options);
+    assert!(kml.contains("Length: 0.19 mi\nElevation gain: 0 ft\nElevation loss: 3 ft\n"));
+
+    let options = ConversionOptions {
+        coordinate_display_precision: 1,
+        ..options
+    };
+    let kml = convert_resource("complete.gpx", &options);
+    assert!(kml.contains("Start: 41.9°N, 12.5°E\n"));
+}
+
+#[test]
+fn metadata_time_as_timestamp() {
+    let kml = convert_resource("metadata_only.gpx", &ConversionOptions::default());
+    assert!(!kml.contains("<TimeStamp>"));
+
+    let options = ConversionOptions {
+        metadata_time_as_timestamp: true,
+        ..Default::default()
+    };
+    let kml = convert_resource("metadata_only.gpx", &options);
+    assert!(kml.contains("<TimeStamp><when>2023-01-02T03:04:05"));
+    assert!(kml.contains("Created 2023-01-02T03:04:05"));
+
+    // Placemarks of features are not affected.
+    let kml = convert_resource("complete.gpx", &options);
+    assert_eq!(kml.matches("<TimeStamp>").count(), 1);
+}
+
+#[test]
+fn max_segment_length() {
+    let kml = convert_resource("complete.gpx", &ConversionOptions::default());
+    let line_strings = kml.matches("<LineString>").count();
+
+    let options = ConversionOptions {
+        max_segment_length_km: Some(1.0),
+        ..Default::default()
+    };
+    let kml = convert_resource("complete.gpx", &options);
+    assert!(kml.matches("<LineString>").count() > line_strings);
+}
+
+#[test]
+fn track_number() {
+    let kml = convert_resource("numbered.gpx", &ConversionOptions::default());
+    assert!(!kml.contains("Route #2"));
+    assert!(!kml.contains("Track #3"));
+
+    let options = ConversionOptions {
+        include_track_number: true,
+        ..Default::default()
+    };
+    let kml = convert_resource("numbered.gpx", &options);
+    assert!(kml.contains("<description>Route #2\n</description>"));
+    assert!(kml.contains("<description>Track #3\nThird track of the day.\n</description>"));
+}
+
+#[test]
+fn atom_feed_mode() {
+    let options = ConversionOptions {
+        atom_feed_mode: true,
+        ..Default::default()
+    };
+    let kml = convert_resource("metadata_only.gpx", &options);
+    let feed = kml
+        .strip_prefix("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n")
+        .expect("XML header missing");
+    assert!(feed.starts_with(r#"<atom:feed xmlns:atom="http://www.w3.org/2005/Atom">"#));
+    assert!(feed.ends_with("</kml></atom:feed>\n"));
+    for expected in [
+        "<atom:title>Metadata Only</atom:title>",
+        "<atom:id>https://example.org/metadata</atom:id>",
+        "<atom:updated>2023-01-02T03:04:05",
+        "<kml ",
+    ] {
+        assert!(feed.contains(expected), "{expected} missing");
+    }
+
+    let kml = convert_resource("empty.gpx", &options);
+    assert!(kml.contains("<atom:id>urn:gpx_kml_convert:"));
+    assert!(kml.contains("<atom:updated>1970-01-01T00:00:00Z</atom:updated>"));
+}
+
+#[test]
+fn description_escape() {
+    let kml = convert_resource("html.gpx", &ConversionOptions::default());
+    assert!(kml.contains("&lt;b&gt;72&lt;/b&gt;"));
+
+    let options = ConversionOptions {
+        description_escape: DescriptionEscape::HtmlCdata,
+        ..Default::default()
+    };
+    let cdata = convert_resource("html.gpx", &options);
+    assert!(cdata.contains("<description><![CDATA[<p>Valley with <b>72</b> waterfalls"));
+    assert!(cdata.contains("<description><![CDATA[Created by gpx_kml_convert\n]]></description>"));
+    parse(&cdata);
+
+    let options = ConversionOptions {
+        description_escape: DescriptionEscape::StripHtml,
+        ..Default::default()
+    };
+    let kml = convert_resource("html.gpx", &options);
+    assert!(kml.contains("<description>Valley with 72 waterfallsalert(1)\n</description>"));
+}
+
+#[cfg(feature = "ammonia")]
+#[test]
+fn description_sanitize() {
+    let options = ConversionOptions {
+        description_escape: DescriptionEscape::SanitizeHtml,
+        ..Default::default()
+    };
+    let kml = convert_resource("html.gpx", &options);
+    assert!(kml.contains("&lt;p&gt;Valley with &lt;b&gt;72&lt;/b&gt; waterfalls&lt;/p&gt;"));
+    assert!(!kml.contains("alert"));
+}
+
+#[test]
+fn omit_zero_altitude() {
+    let source = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx xmlns="http://www.topografix.com/GPX/1/1" version="1.1">
+    <wpt lat="53.5" lon="8.1"><ele>0</ele></wpt>
+    <wpt lat="53.6" lon="8.2"><ele>5</ele></wpt>
+</gpx>
+"#;
+    let kml = convert_str(source, &ConversionOptions::default());
+    assert!(
+        kml.contains("<altitudeMode>absolute</altitudeMode><coordinates>8.1,53.5,0</coordinates>")
+    );
+
+    let options = ConversionOptions {
+        omit_zero_altitude: true,
+        ..Default::default()
+    };
+    let kml = convert_str(source, &options);
+    assert!(kml
+        .contains("<altitudeMode>clampToGround</altitudeMode><coordinates>8.1,53.5</coordinates>"));
+    assert!(
+        kml.contains("<altitudeMode>absolute</altitudeMode><coordinates>8.2,53.6,5</coordinates>")
+    );
+}
+
+#[test]
+fn coordinate_type() {
+    let source = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx xmlns="http://www.topografix.com/GPX/1/1" version="1.1">
+    <wpt lat="48.858222123456789" lon="2.294500987654321"><ele>30.123456789</ele></wpt>
+</gpx>
+"#;
+    let kml = convert_str(source, &ConversionOptions::default());
+    assert!(
+        kml.contains("<coordinates>2.294500987654321,48.85822212345679,30.123456789</coordinates>")
+    );
+
+    let options = ConversionOptions {
+        coordinate_type: CoordinateType::F32,
+        ..Default::default()
+    };
+    let small = convert_str(source, &options);
+    assert!(small.contains("<coordinates>2.294501,48.858223,30.123457</coordinates>"));
+    assert!(small.len() < kml.len());
+}
+
+#[test]
+fn add_checksum() {
+    let kml = convert_resource("complete.gpx", &ConversionOptions::default());
+    assert!(!kml.contains("GPX-CRC32"));
+
+    let options = ConversionOptions {
+        add_checksum: true,
+        ..Default::default()
+    };
+    let kml = convert_resource("complete.gpx", &options);
+    let gpx = std::fs::read(Path::new(RESOURCES).join("complete.gpx")).unwrap();
+    let comment = format!("<!-- GPX-CRC32: {:08X} -->", crc32fast::hash(&gpx));
+    assert_eq!(kml.lines().nth(1), Some(comment.as_str()));
+    // The reader of the `kml` crate does not support comments.
+    parse(&kml.replace(&comment, ""));
+}
+
+#[test]
+fn description_format() {
+    let source = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx xmlns="http://www.topografix.com/GPX/1/1" version="1.1">
+    <wpt lat="48.858222" lon="2.2945">
+        <ele>30</ele>
+        <name>Eiffel &amp; Tower</name>
+        <desc>A &lt;b&gt;large&lt;/b&gt; tower</desc>
+        <link href="https://en.wikipedia.org/wiki/Eiffel_Tower"><text>Wikipedia</text></link>
+        <type>Landmark</type>
+    </wpt>
+</gpx>
+"#;
+    let kml = convert_str(source, &ConversionOptions::default());
+    assert!(kml
+        .contains("<description>A &lt;b&gt;large&lt;/b&gt; tower\nType: Landmark\n</description>"));
+
+    let options = ConversionOptions {
+        description_format: DescriptionFormat::HtmlTable,
+        ..Default::default()
+    };
+    let kml = convert_str(source, &options);
+    assert!(kml.contains(concat!(
+        r#"<description><![CDATA[<table><tr><th align="left">Name</th><td>Eiffel &amp; Tower</td></tr>"#,
+        r#"<tr><th align="left">Description</th><td>A <b>large</b> tower</td></tr>"#,
+        r#"<tr><th align="left">Coordinates</th><td>48.8582°N, 2.2945°E</td></tr>"#,
+        r#"<tr><th align="left">Elevation</th><td>30 m</td></tr>"#,
+        r#"<tr><th align="left">Type</th><td>Landmark</td></tr>"#,
+        r#"<tr><th align="left">Links</th><td><a href="https://en.wikipedia.org/wiki/Eiffel_Tower">Wikipedia</a></td></tr>"#,
+        "</table>]]></description>"
+    )));
+
+    let options = ConversionOptions {
+        description_format: DescriptionFormat::HtmlCard,
+        description_escape: DescriptionEscape::StripHtml,
+        ..Default::default()
+    };
+    let kml = convert_str(source, &options);
+    assert!(kml.contains(
+        r#"<h3 style="margin-top:0">Eiffel &amp; Tower</h3><p>A large tower</p><table>"#
+    ));
+    assert!(!kml.contains(r#"<th align="left">Name</th>"#));
+}
+
+#[test]
+fn input_format() {
+    let source = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx xmlns="http://www.topografix.com/GPX/1/1" version="1.1.0" creator="legacy">
+    <wpt lat="48.858222" lon="2.2945"><name>Eiffel Tower</name></wpt>
+</gpx>
+"#;
+    let mut sink = vec![];
+    let result = convert_with_options(source.as_bytes(), &mut sink, &Default::default());
+    assert!(matches!(result, Err(Error::Gpx(_))));
+
+    let options = ConversionOptions {
+        input_format: InputFormat::Gpx11,
+        ..Default::default()
+    };
+    let kml = convert_str(source, &options);
+    assert!(kml.contains("<name>Eiffel Tower</name>"));
+    assert!(kml.contains("by legacy"));
+
+    let source = r#"<gpx version="1.1"><wpt lat="1" lon="2"></gpx>"#;
+    let result = convert_with_options(source.as_bytes(), &mut sink, &options);
+    assert!(matches!(result, Err(Error::Io(err)) if err.kind() == ErrorKind::InvalidData));
+}
+
+#[test]
+fn name_override() {
+    let options = ConversionOptions {
+        name_override: Some("Upload 2023-06-01".to_string()),
+        ..Default::default()
+    };
+    let kml = convert_resource("complete.gpx", &options);
+    assert!(kml.contains("<Document><open>1</open><name>Upload 2023-06-01</name>"));
+    assert!(!kml.contains("<name>Complete</name>"));
+
+    let kml = convert_resource("empty.gpx", &options);
+    assert!(kml.contains("<name>Upload 2023-06-01</name>"));
+    let options = ConversionOptions {
+        atom_feed_mode: true,
+        ..options
+    };
+    let kml = convert_resource("complete.gpx", &options);
+    assert!(kml.contains("<atom:title>Upload 2023-06-01</atom:title>"));
+}
+
+#[test]
+fn description_affixes() {
+    let source = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx xmlns="http://www.topografix.com/GPX/1/1" version="1.1">
+    <wpt lat="48.858222" lon="2.2945"><desc>Large tower</desc></wpt>
+    <wpt lat="41.89021" lon="12.492231"></wpt>
+</gpx>
+"#;
+    let options = ConversionOptions {
+        description_prefix: Some("© 2024 My Company.".to_string()),
+        description_suffix: Some("Visit example.com for more.".to_string()),
+        ..Default::default()
+    };
+    let kml = convert_str(source, &options);
+    assert!(kml.contains(
+        "<description>© 2024 My Company.\nLarge tower\nVisit example.com for more.</description>"
+    ));
+    assert!(
+        kml.contains("<description>© 2024 My Company.\nVisit example.com for more.</description>")
+    );
+
+    let options = ConversionOptions {
+        description_suffix: Some("<b>End</b>".to_string()),
+        description_format: DescriptionFormat::HtmlTable,
+        ..Default::default()
+    };
+    let kml = convert_str(source, &options);
+    assert!(kml.contains("</table>\n<b>End</b>]]></description>"));
+}
+
+#[test]
+fn visibility() {
+    let kml = convert_resource("complete.gpx", &ConversionOptions::default());
+    assert!(!kml.contains("<visibility>"));
+
+    let options = ConversionOptions {
+        tracks_visible: false,
+        ..Default::default()
+    };
+    let kml = convert_resource("complete.gpx", &options);
+    assert_eq!(kml.matches("<visibility>0</visibility>").count(), 1);
+    let track = &kml[kml.find("<name>Tour through the Alps</name>").unwrap()..];
+    assert!(track[..track.find("</Placemark>").unwrap()].contains("<visibility>0</visibility>"));
+    parse(&kml);
+
+    let options = ConversionOptions {
+        waypoints_visible: false,
+        ..Default::default()
+    };
+    let kml = convert_resource("complete.gpx", &options);
+    assert_eq!(kml.matches("<visibility>0</visibility>").count(), 1);
+    let waypoint = &kml[kml.find("<name>Eiffel Tower</name>").unwrap()..];
+    assert!(
+        waypoint[..waypoint.find("</Placemark>").unwrap()].contains("<visibility>0</visibility>")
+    );
+}
+
+#[test]
+fn use_snippet() {
+    let kml = convert_resource("complete.gpx", &ConversionOptions::default());
+    assert!(!kml.contains("<Snippet"));
+
+    let options = ConversionOptions {
+        use_snippet: true,
+        ..Default::default()
+    };
+    let source = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx xmlns="http://www.topografix.com/GPX/1/1" version="1.1">
+    <wpt lat="48.858222" lon="2.2945"><desc>A &lt;b&gt;large&lt;/b&gt;
+        tower</desc></wpt>
+    <wpt lat="48.8606" lon="2.3376"></wpt>
+    <rte><rtept lat="0" lon="0"/><rtept lat="0" lon="0.01"/><rtept lat="0" lon="0.02"/></rte>
+</gpx>
+"#;
+    let kml = convert_str(source, &options);
+    assert_eq!(kml.matches("<Snippet").count(), 2);
+    assert!(kml.contains(r#"<Snippet maxLines="2">A large tower</Snippet>"#));
+    assert!(kml.contains(r#"<Snippet maxLines="2">3 points, 2.22 km</Snippet>"#));
+
+    let long = "a".repeat(150);
+    let kml = convert_str(
+        &source.replace("tower</desc>", &format!("{long}</desc>")),
+        &options,
+    );
+    let snippet = format!(
+        r#"<Snippet maxLines="2">A large {}…</Snippet>"#,
+        &long[..91]
+    );
+    assert!(kml.contains(&snippet));
+}
+
+#[test]
+fn osmand_compatible() {
+    let source = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx xmlns="http://www.topografix.com/GPX/1/1" version="1.1">
+    <wpt lat="45.8326" lon="6.8652"><name>Mont Blanc</name><sym>Summit</sym></wpt>
+    <wpt lat="45.9237" lon="6.8694"><sym>Geocache</sym></wpt>
+    <rte><rtept lat="45.9237" lon="6.8694"/><rtept lat="45.8326" lon="6.8652"/></rte>
+</gpx>
+"#;
+    let kml = convert_str(source, &ConversionOptions::default());
+    assert!(!kml.contains("osmand"));
+
+    let options = ConversionOptions {
+        osmand_compatible: true,
+        ..Default::default()
+    };
+    let kml = convert_str(source, &options);
+    assert!(kml.contains(r#"xmlns:osmand="https://osmand.net""#));
+    assert!(kml.contains(concat!(
+        "<osmand:icon>natural_peak</osmand:icon>",
+        "<osmand:color>#d00d0d</osmand:color>",
+        "<osmand:background>circle</osmand:background>"
+    )));
+    assert!(kml.contains("<osmand:icon>special_star</osmand:icon>"));
+    assert_eq!(kml.matches("<osmand:icon>").count(), 2);
+    parse(&kml);
+}
+
+#[test]
+fn add_osm_link() {
+    let options = ConversionOptions {
+        add_osm_link: true,
+        osm_zoom: 12,
+        ..Default::default()
+    };
+    let kml = convert_resource("complete.gpx", &options);
+    assert!(kml.contains(concat!(
+        "Type: Point of Interest\n",
+        r#"<a href="https://www.openstreetmap.org/?mlat=48.858222&amp;mlon=2.2945&amp;zoom=12">"#,
+        "View on OpenStreetMap</a>\n]]></description>"
+    )));
+    assert_eq!(kml.matches("View on OpenStreetMap").count(), 1);
+}
+
+#[test]
+fn add_directions_link() {
+    let options = ConversionOptions {
+        add_directions_link: true,
+        ..Default::default()
+    };
+    let kml = convert_resource("complete.gpx", &options);
+    assert!(kml.contains(concat!(
+        r#"<a href="https://maps.google.com/maps?saddr=41.89046,12.49122&amp;daddr=41.88962,12.49265"#,
+        r#"&amp;waypoints=41.89065,12.49329">Directions on Google Maps</a>"#
+    )));
+    assert_eq!(kml.matches("Directions on Google Maps").count(), 1);
+
+    let points: String = (0..12)
+        .map(|i| format!(r#"<rtept lat="1" lon="{i}"/>"#))
+        .collect();
+    let source = format!(
+        r#"<gpx version="1.1"><rte>{points}</rte><rte><rtept lat="1" lon="2"/></rte></gpx>"#
+    );
+    let kml = convert_str(&source, &options);
+    let waypoints: Vec<_> = [1, 2, 3, 4, 6, 7, 8, 10]
+        .iter()
+        .map(|i| format!("1,{i}"))
+        .collect();
+    assert!(kml.contains(&format!(
+        "saddr=1,0&amp;daddr=1,11&amp;waypoints={}\"",
+        waypoints.join("%7C")
+    )));
+    assert_eq!(kml.matches("Directions on Google Maps").count(), 1);
+}
+
+#[test]
+fn split_on_elevation_change() {
+    let source = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx xmlns="http://www.topografix.com/GPX/1/1" version="1.1">
+    <trk><name>Ski Day</name><trkseg>
+        <trkpt lat="46.00" lon="7.00"><ele>1500</ele></trkpt>
+        <trkpt lat="46.01" lon="7.00"><ele>2000</ele></trkpt>
+        <trkpt lat="46.02" lon="7.00"><ele>1995</ele></trkpt>
+        <trkpt lat="46.03" lon="7.00"><ele>1600</ele></trkpt>
+        <trkpt lat="46.04" lon="7.00"><ele>2100</ele></trkpt>
+    </trkseg></trk>
+</gpx>
+"#;
+    let kml = convert_str(source, &ConversionOptions::default());
+    assert!(!kml.contains("<Folder>"));
+
+    let options = ConversionOptions {
+        split_on_elevation_change: Some(10.0),
+        ..Default::default()
+    };
+    let kml = convert_str(source, &options);
+    let ascents = &kml[kml.find("<Folder><name>Ascents</name>").unwrap()..];
+    let ascents = &ascents[..ascents.find("</Folder>").unwrap()];
+    assert_eq!(ascents.matches("<name>Ski Day</name>").count(), 1);
+    assert_eq!(ascents.matches("<LineString>").count(), 2);
+    let descents = &kml[kml.find("<Folder><name>Descents</name>").unwrap()..];
+    assert!(
+        descents.contains("<coordinates>7,46.01,2000\n7,46.02,1995\n7,46.03,1600</coordinates>")
+    );
+    parse(&kml);
+
+    let source = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx xmlns="http://www.topografix.com/GPX/1/1" version="1.1">
+    <trk><name>Climb</name><trkseg>
+        <trkpt lat="46.00" lon="7.00"><ele>1500</ele></trkpt>
+        <trkpt lat="46.01" lon="7.00"><ele>2000</ele></trkpt>
+    </trkseg></trk>
+    <trk><name>Flat</name><trkseg>
+        <trkpt lat="46.00" lon="7.00"/><trkpt lat="46.01" lon="7.00"/>
+    </trkseg></trk>
+</gpx>
+"#;
+    let kml = convert_str(source, &options);
+    assert!(kml.contains("<Folder><name>Ascents</name>"));
+    assert!(!kml.contains("<name>Descents</name>"));
+    let flat = kml.find("<name>Flat</name>").unwrap();
+    assert!(flat > kml.rfind("</Folder>").unwrap());
+    parse(&kml);
+}
+
+#[test]
+fn detect_loops() {
+    let source = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx xmlns="http://www.topografix.com/GPX/1/1" version="1.1">
+    <rte><name>Lake Loop</name>
+        <rtept lat="47.0" lon="8.0"/><rtept lat="47.01" lon="8.0"/><rtept lat="47.0005" lon="8.0"/>
+    </rte>
+    <rte><rtept lat="47.0" lon="8.0"/><rtept lat="47.01" lon="8.0"/></rte>
+    <trk><trkseg><trkpt lat="47.0" lon="8.0"/><trkpt lat="47.01" lon="8.0"/></trkseg>
+        <trkseg><trkpt lat="47.0" lon="8.001"/></trkseg></trk>
+</gpx>
+"#;
+    let options = ConversionOptions {
+        detect_loops: true,
+        ..Default::default()
+    };
+    let kml = convert_str(source, &options);
+    assert!(kml.contains("<name>Lake Loop</name>"));
+    assert!(!kml.contains("(Loop)"));
+    assert_eq!(
+        kml.matches(
+            r#"<ExtendedData><Data name="is_loop"><value>true</value></Data></ExtendedData>"#
+        )
+        .count(),
+        2
+    );
+    parse(&kml);
+
+    let options = ConversionOptions {
+        loop_threshold_m: 10.0,
+        ..options
+    };
+    let kml = convert_str(source, &options);
+    assert_eq!(kml.matches("<ExtendedData>").count(), 0);
+}
+
+#[test]
+fn merge_tracks_by_name() {
+    let source = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx xmlns="http://www.topografix.com/GPX/1/1" version="1.1">
+    <trk><name>Morning Run</name><desc>Monday</desc>
+        <trkseg><trkpt lat="47.0" lon="8.0"/><trkpt lat="47.01" lon="8.0"/></trkseg></trk>
+    <trk><trkseg><trkpt lat="47.0" lon="8.0"/><trkpt lat="47.01" lon="8.0"/></trkseg></trk>
+    <trk><name>Evening Walk</name>
+        <trkseg><trkpt lat="47.0" lon="8.0"/><trkpt lat="47.01" lon="8.0"/></trkseg></trk>
+    <trk><name>Morning Run</name><desc>Tuesday</desc>
+        <trkseg><trkpt lat="47.1" lon="8.1"/><trkpt lat="47.11" lon="8.1"/></trkseg></trk>
+    <trk><trkseg><trkpt lat="47.0" lon="8.0"/><trkpt lat="47.01" lon="8.0"/></trkseg></trk>
+</gpx>
+"#;
+    let kml = convert_str(source, &ConversionOptions::default());
+    assert_eq!(kml.matches("<Placemark>").count(), 5);
+
+    let options = ConversionOptions {
+        merge_tracks_by_name: true,
+        ..Default::default()
+    };
+    let kml = convert_str(source, &options);
+    assert_eq!(kml.matches("<Placemark>").count(), 4);
+    assert_eq!(kml.matches("<name>Morning Run</name>").count(), 1);
+    assert!(kml.contains("Monday"));
+    assert!(!kml.contains("Tuesday"));
+    let run = &kml[kml.find("<name>Morning Run</name>").unwrap()..];
+    let run = &run[..run.find("</Placemark>").unwrap()];
+    assert_eq!(run.matches("<LineString>").count(), 2);
+    assert!(run.contains("8.1,47.11"));
+
+    // Tracks are merged across the documents, too.
+    let mut sink = vec![];
+    let _ = convert_to_multi_document([source.as_bytes(), source.as_bytes()], &mut sink, &options)
+        .expect("conversion failed");
+    let kml = String::from_utf8(sink).unwrap();
+    assert_eq!(kml.matches("<Document>").count(), 2);
+    assert_eq!(kml.matches("<Placemark>").count(), 6);
+    assert_eq!(kml.matches("<name>Morning Run</name>").count(), 1);
+    let run = &kml[kml.find("<name>Morning Run</name>").unwrap()..];
+    let run = &run[..run.find("</Placemark>").unwrap()];
+    assert_eq!(run.matches("<LineString>").count(), 4);
+    let second = &kml[kml.rfind("<Document>").unwrap()..];
+    assert!(!second.contains("<name>Evening Walk</name>"));
+}
+
+#[test]
+fn infer_activity_from_speed() {
+    let source = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx xmlns="http://www.topografix.com/GPX/1/1" version="1.1">
+    <trk><trkseg>
+        <trkpt lat="47.0" lon="8.0"><time>2023-05-01T08:00:00Z</time></trkpt>
+        <trkpt lat="47.01" lon="8.0"><time>2023-05-01T08:10:00Z</time></trkpt>
+    </trkseg></trk>
+    <trk><type>Swimming</type><trkseg>
+        <trkpt lat="47.0" lon="8.0"><time>2023-05-01T08:00:00Z</time></trkpt>
+        <trkpt lat="47.01" lon="8.0"><time>2023-05-01T08:01:00Z</time></trkpt>
+    </trkseg></trk>
+    <trk><trkseg><trkpt lat="47.0" lon="8.0"/><trkpt lat="47.01" lon="8.0"/></trkseg></trk>
+</gpx>
+"#;
+    let kml = convert_str(source, &ConversionOptions::default());
+    assert_eq!(kml.matches("Type: ").count(), 1);
+
+    let options = ConversionOptions {
+        infer_activity_from_speed: true,
+        activity_colors: [("Hiking".to_string(), "ff00ff00".to_string())].into(),
+        ..Default::default()
+    };
+    let kml = convert_str(source, &options);
+    assert_eq!(kml.matches("Type: ").count(), 2);
+    assert!(kml.contains("Type: hiking\n"));
+    assert!(kml.contains("Type: Swimming\n"));
+    assert_eq!(kml.matches("<color>ff00ff00</color>").count(), 1);
+}
+
+#[test]
+fn privacy_radius() {
+    let source = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx xmlns="http://www.topografix.com/GPX/1/1" version="1.1">
+    <rte><rtept lat="0" lon="0"/><rtept lat="0" lon="0.01"/></rte>
+    <trk>
+        <trkseg>
+            <trkpt lat="0" lon="0"/><trkpt lat="0" lon="0.001"/><trkpt lat="0" lon="0.01"/>
+            <trkpt lat="0" lon="0.02"/><trkpt lat="0" lon="0.029"/><trkpt lat="0" lon="0.03"/>
+        </trkseg>
+        <trkseg><trkpt lat="1" lon="0"/><trkpt lat="1" lon="0.001"/></trkseg>
+    </trk>
+</gpx>
+"#;
+    let options = ConversionOptions {
+        privacy_radius_m: Some(500.0),
+        ..Default::default()
+    };
+    let mut sink = vec![];
+    let report = convert_with_options(source.as_bytes(), &mut sink, &options).unwrap();
+    let kml = String::from_utf8(sink).unwrap();
+    assert!(kml.contains("<coordinates>0,0\n0.01,0</coordinates>"));
+    assert!(kml.contains("<coordinates>0.01,0\n0.02,0</coordinates>"));
+    assert_eq!(kml.matches("<LineString>").count(), 2);
+    // The trimmed points are not revealed by the report either.
+    assert_eq!(report.bounding_box.max_lat, 0.0);
+    assert_eq!(report.bounding_box.max_lon, 0.02);
+}
+
+#[test]
+fn geofence_exclusions() {
+    let source = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx xmlns="http://www.topografix.com/GPX/1/1" version="1.1">
+    <wpt lat="0.5" lon="0.5"/>
+    <trk><trkseg>
+        <trkpt lat="0" lon="0"/><trkpt lat="0.5" lon="0.5"/><trkpt lat="0.6" lon="0.6"/>
+        <trkpt lat="1" lon="1"/><trkpt lat="2" lon="2"/>
+    </trkseg></trk>
+</gpx>
+"#;
+    let options = ConversionOptions {
+        geofence_exclusions: vec![
+            BoundingBox {
+                min_lat: 0.0,
+                max_lat: 1.0,
+                min_lon: 0.0,
+                max_lon: 1.0,
+            },
+            BoundingBox {
+                min_lat: 10.0,
+                max_lat: 11.0,
+                min_lon: 10.0,
+                max_lon: 11.0,
+            },
+        ],
+        ..Default::default()
+    };
+    let mut sink = vec![];
+    let report = convert_with_options(source.as_bytes(), &mut sink, &options).unwrap();
+    let kml = String::from_utf8(sink).unwrap();
+    assert!(kml.contains("<coordinates>0,0\n1,1\n2,2</coordinates>"));
+    assert!(kml.contains("<coordinates>0.5,0.5</coordinates>"));
+    assert_eq!(report.bounding_box.min_lat, 0.0);
+    assert_eq!(report.bounding_box.max_lat, 2.0);
+}
+
+#[test]
+fn max_track_age_days() {
+    let source = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx xmlns="http://www.topografix.com/GPX/1/1" version="1.1">
+    <trk><name>Old</name><trkseg>
+        <trkpt lat="0" lon="0"><time>2001-01-01T00:00:00Z</time></trkpt>
+    </trkseg></trk>
+    <trk><name>Future</name><trkseg>
+        <trkpt lat="0" lon="0"><time>2001-01-01T00:00:00Z</time></trkpt>
+        <trkpt lat="0" lon="0"><time>2999-01-01T00:00:00Z</time></trkpt>
+    </trkseg></trk>
+    <trk><name>Untimed</name><trkseg><trkpt lat="0" lon="0"/></trkseg></trk>
+</gpx>
+"#;
+    let kml = convert_str(source, &ConversionOptions::default());
+    assert!(kml.contains("<name>Old</name>"));
+
+    let options = ConversionOptions {
+        max_track_age_days: Some(365),
+        ..Default::default()
+    };
+    let mut sink = vec![];
+    let report = convert_with_options(source.as_bytes(), &mut sink, &options).unwrap();
+    let kml = String::from_utf8(sink).unwrap();
+    assert!(!kml.contains("<name>Old</name>"));
+    assert!(kml.contains("<name>Future</name>"));
+    assert!(kml.contains("<name>Untimed</name>"));
+    let [Warning::TrackDroppedTooOld { name, age_days }] = report.warnings.as_slice() else {
+        panic!("unexpected warnings: {:?}", report.warnings);
+    };
+    assert_eq!(name, "Old");
+    assert!(*age_days > 365.0 * 20.0);
+}
+
+#[test]
+fn min_length() {
+    let source = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx xmlns="http://www.topografix.com/GPX/1/1" version="1.1">
+    <rte><name>Short route</name><rtept lat="0" lon="0"/><rtept lat="0" lon="0.00001"/></rte>
+    <trk><name>Drift</name>
+        <trkseg><trkpt lat="0" lon="0"/><trkpt lat="0" lon="0.00005"/></trkseg>
+        <trkseg><trkpt lat="0" lon="0"/><trkpt lat="0" lon="0.00005"/></trkseg>
+    </trk>
+    <trk><name>Walk</name><trkseg><trkpt lat="0" lon="0"/><trkpt lat="0" lon="0.01"/></trkseg></trk>
+</gpx>
+"#;
+    let options = ConversionOptions {
+        min_track_length_m: Some(10.0),
+        ..Default::default()
+    };
+    let kml = convert_str(source, &options);
+    // Each segment is shorter than 10 m, but both together are not.
+    assert!(kml.contains("<name>Drift</name>"));
+    assert!(kml.contains("<name>Short route</name>"));
+
+    let options = ConversionOptions {
+        min_track_length_m: Some(20.0),
+        min_route_length_m: Some(2.0),
+        ..Default::default()
+    };
+    let mut sink = vec![];
+    let report = convert_with_options(source.as_bytes(), &mut sink, &options).unwrap();
+    let kml = String::from_utf8(sink).unwrap();
+    assert!(!kml.contains("<name>Drift</name>"));
+    assert!(kml.contains("<name>Walk</name>"));
+    assert!(!kml.contains("<name>Short route</name>"));
+    assert_eq!(
+        (
+            report.waypoint_count,
+            report.route_count,
+            report.track_count
+        ),
+        (0, 0, 1)
+    );
+    assert_eq!(report.warnings.len(), 2);
+    assert!(matches!(
+        &report.warnings[0],
+        Warning::TrackDroppedTooShort { name, length_m }
+            if name == "Drift" && (11.0..11.2).contains(length_m)
+    ));
+    assert!(matches!(
+        &report.warnings[1],
+        Warning::RouteDroppedTooShort { name, length_m }
+            if name == "Short route" && (1.0..1.2).contains(length_m)
+    ));
+}
+
+#[test]
+fn min_track_duration() {
+    let source = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx xmlns="http://www.topografix.com/GPX/1/1" version="1.1">
+    <trk><name>Junk</name><trkseg>
+        <trkpt lat="0" lon="0"><time>2023-06-01T08:00:00Z</time></trkpt>
+        <trkpt lat="0" lon="1"><time>2023-06-01T08:00:59Z</time></trkpt>
+    </trkseg></trk>
+    <trk><name>Walk</name><trkseg>
+        <trkpt lat="0" lon="0"><time>2023-06-01T08:00:00Z</time></trkpt>
+        <trkpt lat="0" lon="0"><time>2023-06-01T08:01:00Z</time></trkpt>
+    </trkseg></trk>
+    <trk><name>Untimed</name><trkseg><trkpt lat="0" lon="0"/></trkseg></trk>
+</gpx>
+"#;
+    let options = ConversionOptions {
+        min_track_duration: Some(Duration::from_secs(60)),
+        ..Default::default()
+    };
+    let mut sink = vec![];
+    let report = convert_with_options(source.as_bytes(), &mut sink, &options).unwrap();
+    let kml = String::from_utf8(sink).unwrap();
+    assert_eq!(
+        report.warnings,
+        [Warning::TrackDroppedTooBrief {
+            name: "Junk".to_string(),
+            duration_s: 59.0
+        }]
+    );
+    assert!(!kml.contains("<name>Junk</name>"));
+    assert!(kml.contains("<name>Walk</name>"));
+    assert!(kml.contains("<name>Untimed</name>"));
+}
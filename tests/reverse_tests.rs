@@ -0,0 +1,138 @@
+// Copyright 2023 Viktor Reusch
+//
+// This file is part of gpx_kml_convert.
+//
+// gpx_kml_convert is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, either version 3 of the License, or (at your
+// option) any later version.
+//
+// gpx_kml_convert is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License
+// for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with gpx_kml_convert. If not, see <https://www.gnu.org/licenses/>.
+
+//! Tests for converting KML back to GPX with [`kml_to_gpx_structs`] and
+//! [`validate_kml_roundtrip`].
+
+use std::{fs::File, path::Path};
+
+use gpx::Gpx;
+use gpx_kml_convert::{
+    convert_with_options, kml_to_gpx_structs, validate_kml_roundtrip, ConversionOptions, Error,
+};
+use kml::{Kml, KmlDocument, KmlReader};
+
+const RESOURCES: &str = "./resources/";
+
+/// Read the GPX file `name` from the resources.
+fn read_resource(name: &str) -> Gpx {
+    gpx::read(File::open(Path::new(RESOURCES).join(name)).expect("resource not found"))
+        .expect("resource is invalid")
+}
+
+/// Convert the GPX file `name` with `options` and parse the KML output.
+fn convert_resource(name: &str, options: &ConversionOptions) -> KmlDocument {
+    let source = File::open(Path::new(RESOURCES).join(name)).expect("resource not found");
+    let mut sink = vec![];
+    let _ = convert_with_options(source, &mut sink, options).expect("conversion failed");
+    parse(&String::from_utf8(sink).expect("KML data is not valid UTF-8"))
+}
+
+/// Parse the KML `document`.
+fn parse(document: &str) -> KmlDocument {
+    match KmlReader::<_, f64>::from_string(document).read() {
+        Ok(Kml::KmlDocument(document)) => document,
+        result => panic!("unexpected KML {result:?}"),
+    }
+}
+
+#[test]
+fn roundtrip() {
+    let original = read_resource("complete.gpx");
+    let kml = convert_resource("complete.gpx", &ConversionOptions::default());
+    let gpx = kml_to_gpx_structs(&kml).expect("conversion failed");
+
+    assert_eq!(gpx.waypoints.len(), original.waypoints.len());
+    for (waypoint, original) in gpx.waypoints.iter().zip(&original.waypoints) {
+        assert_eq!(waypoint.name, original.name);
+        assert_eq!(waypoint.point(), original.point());
+        assert_eq!(waypoint.elevation, original.elevation);
+        assert_eq!(waypoint.links.len(), original.links.len());
+    }
+
+    assert_eq!(gpx.routes.len(), original.routes.len());
+    assert_eq!(gpx.routes[0].name, original.routes[0].name);
+    assert_eq!(gpx.routes[0].points.len(), original.routes[0].points.len());
+
+    assert_eq!(gpx.tracks.len(), original.tracks.len());
+    let segments: Vec<_> = gpx.tracks[0]
+        .segments
+        .iter()
+        .map(|s| s.points.len())
+        .collect();
+    let expected: Vec<_> = original.tracks[0]
+        .segments
+        .iter()
+        .map(|s| s.points.len())
+        .collect();
+    assert_eq!(segments, expected);
+
+    let metadata = gpx.metadata.expect("metadata missing");
+    assert_eq!(metadata.name, original.metadata.unwrap().name);
+}
+
+#[test]
+fn timestamps() {
+    let options = ConversionOptions {
+        metadata_time_as_timestamp: true,
+        ..Default::default()
+    };
+    let kml = convert_resource("metadata_only.gpx", &options);
+    let gpx = kml_to_gpx_structs(&kml).expect("conversion failed");
+    assert_eq!(
+        gpx.metadata.and_then(|m| m.time),
+        read_resource("metadata_only.gpx").metadata.unwrap().time
+    );
+
+    let kml = parse(
+        r#"<kml><Placemark><TimeStamp><when>2023-01-02T03:04:05Z</when></TimeStamp>
+        <Point><coordinates>1,2</coordinates></Point></Placemark></kml>"#,
+    );
+    let gpx = kml_to_gpx_structs(&kml).expect("conversion failed");
+    let time = gpx.waypoints[0].time.expect("time missing");
+    assert_eq!(time.format().unwrap(), "2023-01-02T03:04:05.000000000Z");
+
+    let kml = parse(
+        r#"<kml><Placemark><TimeStamp><when>yesterday</when></TimeStamp>
+        <Point><coordinates>1,2</coordinates></Point></Placemark></kml>"#,
+    );
+    let err = kml_to_gpx_structs(&kml).expect_err("invalid time was accepted");
+    assert!(matches!(err, Error::InvalidKml(_)));
+    assert!(format!("{err}").starts_with("invalid KML: invalid time \"yesterday\""));
+}
+
+#[test]
+fn validate_roundtrip() {
+    for name in ["complete.gpx", "unicode.gpx", "numbered.gpx"] {
+        let gpx = std::fs::read(Path::new(RESOURCES).join(name)).unwrap();
+        let report = validate_kml_roundtrip(&gpx).expect("roundtrip failed");
+        assert!(report.placemark_count_match, "{name}");
+        assert!(report.max_coordinate_delta < 1e-9, "{name}");
+        assert_eq!(report.name_preservation_rate, 1.0, "{name}");
+    }
+
+    let gpx = std::fs::read(Path::new(RESOURCES).join("empty.gpx")).unwrap();
+    let report = validate_kml_roundtrip(&gpx).expect("roundtrip failed");
+    assert!(report.placemark_count_match);
+    assert_eq!(report.max_coordinate_delta, 0.0);
+    assert_eq!(report.name_preservation_rate, 1.0);
+
+    assert!(matches!(
+        validate_kml_roundtrip(b"<gpx>"),
+        Err(Error::Gpx(_))
+    ));
+}
@@ -0,0 +1,35 @@
+// Copyright 2023 Viktor Reusch
+//
+// This file is part of gpx_kml_convert.
+//
+// gpx_kml_convert is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, either version 3 of the License, or (at your
+// option) any later version.
+//
+// gpx_kml_convert is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License
+// for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with gpx_kml_convert. If not, see <https://www.gnu.org/licenses/>.
+
+//! Compile-time checks that the public types can be shared between threads.
+
+use gpx_kml_convert::{
+    BoundingBox, ConversionOptions, ConversionReport, DistanceUnits, Error, GpxAccumulator, Warning,
+};
+
+fn assert_send_sync<T: Send + Sync>() {}
+
+#[test]
+fn send_sync() {
+    assert_send_sync::<BoundingBox>();
+    assert_send_sync::<ConversionOptions>();
+    assert_send_sync::<ConversionReport>();
+    assert_send_sync::<DistanceUnits>();
+    assert_send_sync::<Error>();
+    assert_send_sync::<GpxAccumulator>();
+    assert_send_sync::<Warning>();
+}
@@ -17,15 +17,58 @@
 
 //! This is a WASM wrapper for `gpx_kml_convert`.
 
+use gpx_kml_convert::{convert_gpx, ConversionOptions, Error};
 use wasm_bindgen::{prelude::wasm_bindgen, JsError};
 
-/// This wraps `gpx_kml_convert::convert` for interfacing with JS.
+/// Log a formatted message to the browser console.
+///
+/// This expands to nothing unless the `debug-logging` feature is enabled.
+macro_rules! log {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "debug-logging")]
+        web_sys::console::log_1(&format!($($arg)*).into());
+    };
+}
+
+/// Initialize the WASM module.
+///
+/// This should be called once from JS before any other function. It sets up
+/// forwarding of Rust panics to the browser console if the
+/// `console_error_panic_hook` feature is enabled.
 #[wasm_bindgen]
-pub fn convert(source: &[u8]) -> Result<Box<[u8]>, JsError> {
+pub fn init() {
     #[cfg(feature = "console_error_panic_hook")]
     console_error_panic_hook::set_once();
+}
+
+/// This wraps `gpx_kml_convert::convert` for interfacing with JS.
+#[wasm_bindgen]
+pub fn convert(source: &[u8]) -> Result<Box<[u8]>, JsError> {
+    log!("conversion started with {} bytes", source.len());
+    let gpx = gpx::read(source).map_err(Error::from)?;
+    log!("GPX parsed successfully");
 
     let mut sink = vec![];
-    gpx_kml_convert::convert(source, &mut sink)?;
+    log!("KML writing started");
+    let _ = convert_gpx(gpx, &mut sink, &ConversionOptions::default())?;
+    log!("KML writing complete with {} bytes", sink.len());
     Ok(sink.into_boxed_slice())
 }
+
+/// Like [`convert`] but accepts the GPX file as JS string.
+///
+/// This saves JS callers from encoding text, e.g., from
+/// `FileReader.readAsText`, with a `TextEncoder` first.
+#[wasm_bindgen]
+pub fn convert_string(source: String) -> Result<Box<[u8]>, JsError> {
+    convert(source.as_bytes())
+}
+
+/// Like [`convert`] but returns the KML file as JS string.
+///
+/// This saves JS callers from decoding the result with a `TextDecoder`.
+#[wasm_bindgen]
+pub fn convert_to_string(source: &[u8]) -> Result<String, JsError> {
+    // The KML writer only produces UTF-8, so this should never fail.
+    Ok(String::from_utf8(convert(source)?.into_vec())?)
+}
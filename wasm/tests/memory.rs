@@ -0,0 +1,49 @@
+// Copyright 2022 Viktor Reusch
+//
+// This file is part of gpx_kml_convert.
+//
+// gpx_kml_convert is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, either version 3 of the License, or (at your
+// option) any later version.
+//
+// gpx_kml_convert is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License
+// for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with gpx_kml_convert. If not, see <https://www.gnu.org/licenses/>.
+
+//! Check that repeated conversions do not leak WASM memory.
+//!
+//! Run with `wasm-pack test --headless --firefox`.
+
+#![cfg(target_arch = "wasm32")]
+
+use std::arch::wasm32::memory_size;
+
+use gpx_kml_convert_wasm::convert;
+use wasm_bindgen_test::{wasm_bindgen_test, wasm_bindgen_test_configure};
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+const SOURCE: &[u8] = include_bytes!("../../resources/complete.gpx");
+const ITERATIONS: usize = 1000;
+
+#[wasm_bindgen_test]
+fn no_memory_growth() {
+    // The first conversion may grow the memory for the allocator's own use.
+    assert!(convert(SOURCE).is_ok(), "conversion failed");
+    let pages = memory_size(0);
+
+    for _ in 0..ITERATIONS {
+        // `JsError` does not implement `Debug`, which rules out `expect`.
+        let Ok(kml) = convert(SOURCE) else {
+            panic!("conversion failed");
+        };
+        assert!(!kml.is_empty());
+    }
+
+    assert_eq!(memory_size(0), pages);
+}
@@ -0,0 +1,85 @@
+// Copyright 2022 Viktor Reusch
+//
+// This file is part of gpx_kml_convert.
+//
+// gpx_kml_convert is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, either version 3 of the License, or (at your
+// option) any later version.
+//
+// gpx_kml_convert is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+// FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License
+// for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with gpx_kml_convert. If not, see <https://www.gnu.org/licenses/>.
+
+//! Browser tests for the JS interface.
+//!
+//! Run with `wasm-pack test --headless --chrome`.
+
+#![cfg(target_arch = "wasm32")]
+
+use gpx_kml_convert_wasm::{convert, convert_string, convert_to_string};
+use kml::{Kml, KmlReader};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_test::{wasm_bindgen_test, wasm_bindgen_test_configure};
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+const SOURCE: &[u8] = include_bytes!("../../resources/complete.gpx");
+const XML_HEAD: &[u8] = br#"<?xml version="1.0" encoding="UTF-8"?>"#;
+
+fn parse(kml: &[u8]) -> Kml {
+    let kml = std::str::from_utf8(kml).expect("KML output is not valid UTF-8");
+    KmlReader::<_, f64>::from_string(kml)
+        .read()
+        .expect("KML output could not be parsed")
+}
+
+#[wasm_bindgen_test]
+fn valid() {
+    // `JsError` does not implement `Debug`, which rules out `expect`.
+    let Ok(kml) = convert(SOURCE) else {
+        panic!("conversion failed");
+    };
+    assert!(!kml.is_empty());
+    assert!(kml.starts_with(XML_HEAD));
+}
+
+#[wasm_bindgen_test]
+fn valid_string() {
+    let source = std::str::from_utf8(SOURCE).expect("resource is not valid UTF-8");
+    let Ok(kml) = convert_string(source.to_string()) else {
+        panic!("conversion failed");
+    };
+    let Ok(expected) = convert(SOURCE) else {
+        panic!("conversion failed");
+    };
+
+    // Compare the parsed documents because the order of attributes may vary.
+    assert_eq!(parse(&kml), parse(&expected));
+}
+
+#[wasm_bindgen_test]
+fn valid_to_string() {
+    let Ok(kml) = convert_to_string(SOURCE) else {
+        panic!("conversion failed");
+    };
+    assert!(kml.starts_with(std::str::from_utf8(XML_HEAD).unwrap()));
+    assert!(kml.contains("<Document>"));
+}
+
+#[wasm_bindgen_test]
+fn invalid() {
+    let Err(err) = convert(b"<gpx>") else {
+        panic!("invalid GPX was accepted");
+    };
+
+    let err: js_sys::Error = JsValue::from(err)
+        .dyn_into()
+        .expect("error is not a JS Error");
+    let message = String::from(err.message());
+    assert!(message.starts_with("reading GPX failed: "));
+}